@@ -3,4 +3,4 @@
 
 # The first argument is the depth, the second argument is the FEN, the third argument is moves (not mendotory)
 # https://github.com/agausmann/perftree
-./target/release/persa_chess "$1" "$2" "$3"
+./target/release/persa perft "$1" "$2" "$3"
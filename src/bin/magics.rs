@@ -0,0 +1,68 @@
+use persa_chess::board_components::{MagicNum, Square};
+use persa_chess::magics::{search_magics, MagicOptions, MagicSet, Slider};
+use std::env;
+use std::fs;
+use std::process::exit;
+use std::str::FromStr;
+
+const USAGE: &str = "usage: magics [rook|bishop] [seed N] [tries N] [bits N] [fancy] [verify] [output FILE]";
+
+fn print_progress(slider: Slider, square: Square, bits: u32, magic: MagicNum) {
+    println!("{} {} bits {} magic 0x{:X}", slider.get_name(), square, bits, magic.0);
+}
+
+fn exit_with(message: String) -> ! {
+    eprintln!("{}", message);
+    exit(1);
+}
+
+fn parse_value<T: FromStr>(option: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| exit_with(format!("invalid value {} for {}\n{}", value, option, USAGE)))
+}
+
+// searches rook and bishop magics and prints them as the functions of constants::board_constants,
+// verify only checks the magics the engine is built with
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut sliders = vec![Slider::Rook, Slider::Bishop];
+    let mut options = MagicOptions::new().set_report(print_progress);
+    let (mut verify, mut output) = (false, None);
+    let mut tokens = args.iter();
+    while let Some(token) = tokens.next() {
+        match token.as_str() {
+            "rook"   => sliders = vec![Slider::Rook],
+            "bishop" => sliders = vec![Slider::Bishop],
+            "fancy"  => options = options.set_fancy(true),
+            "verify" => verify = true,
+            "help"   => return println!("{}", USAGE),
+            option @ ("seed" | "tries" | "bits" | "output") => {
+                let value = tokens.next().unwrap_or_else(|| exit_with(format!("missing value for {}\n{}", option, USAGE)));
+                options = match option {
+                    "seed"  => options.set_seed(parse_value(option, value)),
+                    "tries" => options.set_tries(parse_value(option, value)),
+                    "bits"  => options.set_bits(Some(parse_value(option, value))),
+                    _       => { output = Some(value.clone()); options },
+                };
+            },
+            _ => exit_with(format!("unknown option {}\n{}", token, USAGE)),
+        }
+    }
+
+    let mut source = String::new();
+    for slider in sliders {
+        let set = if verify { MagicSet::current(slider) } else { search_magics(slider, &options).unwrap_or_else(|err| exit_with(err)) };
+        match set.verify() {
+            Ok(()) => println!("{} magics verified, table size {} entries ({} KB)", slider.get_name(), set.get_table_size(), set.get_table_size() * 8 / 1024),
+            Err(err) => exit_with(err),
+        }
+        if !verify { source += &set.get_rust_source(); }
+    }
+    if verify { return; }
+    match output {
+        Some(path) => {
+            fs::write(&path, &source).unwrap_or_else(|err| exit_with(format!("can not write {}: {}", path, err)));
+            println!("magics written to {}", path);
+        },
+        None => println!("\n{}", source),
+    }
+}
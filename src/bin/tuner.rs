@@ -0,0 +1,36 @@
+use persa_chess::eveluation_params::EvalParams;
+use persa_chess::tuner::{load_entries, Tuner};
+use std::env;
+
+// usage: tuner <positions file> <output params file> [threads N] [iterations N] [step N] [params start_file]
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        println!("usage: tuner <positions file> <output params file> [threads N] [iterations N] [step N] [params start_file]");
+        return;
+    }
+    let mut threads = 1;
+    let mut iterations = 100;
+    let mut step = 1;
+    let mut params = EvalParams::new();
+    let mut options = args[3..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().expect("missing value for option");
+        match option.as_str() {
+            "threads"    => threads    = value.parse().expect("invalid thread count"),
+            "iterations" => iterations = value.parse().expect("invalid iteration count"),
+            "step"       => step       = value.parse().expect("invalid step"),
+            "params"     => params     = EvalParams::load(value).unwrap_or_else(|err| panic!("{}", err)),
+            _            => panic!("unknown option {}", option),
+        }
+    }
+
+    let entries = load_entries(&args[1], &params).unwrap_or_else(|err| panic!("{}", err));
+    println!("loaded {} positions", entries.len());
+    let mut tuner = Tuner::new(entries, params, threads);
+    println!("fitted K: {:.4}", tuner.fit_k());
+    match tuner.tune(iterations, step, &args[2]) {
+        Ok(error) => println!("final error: {:.8}, parameters written to {}", error, args[2]),
+        Err(err)  => println!("{}", err),
+    }
+}
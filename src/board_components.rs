@@ -1,19 +1,63 @@
-use std::{ops::{BitAnd, BitOr, Not, Add, Mul, Index, IndexMut}, fmt::Display};
+use std::{ops::{BitAnd, BitOr, BitXor, Not, Add, Sub, Mul, Index, IndexMut}, fmt::Display, str::FromStr};
 use crate::{impl_op, impl_indv_bit_op};
+use crate::constants::board_constants::{A_FILE, H_FILE, RANK1};
+use crate::pieces::tables::{BETWEEN_TABLE, LINE_TABLE};
+use crate::position::Error;
 
 #[derive(Copy, Clone, Debug, PartialEq, Default)]
 pub struct BitBoard (pub u64);
 
 impl_op!(BitBoard, BitAnd, bitand, 0);
 impl_op!(BitBoard, BitOr, bitor, 0);
+impl_op!(BitBoard, BitXor, bitxor, 0);
 impl_indv_bit_op!(BitBoard, Not, not, 0);
 
+// set difference, the squares of self that are not in rhs
+impl Sub for BitBoard {
+    type Output = Self;
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 & !rhs.0)
+    }
+}
+
+impl From<Square> for BitBoard {
+    #[inline(always)]
+    fn from(square: Square) -> Self {
+        Self(1 << square.0)
+    }
+}
+
+impl BitAnd<Square> for BitBoard {
+    type Output = Self;
+    #[inline(always)]
+    fn bitand(self, rhs: Square) -> Self::Output {
+        self & BitBoard::from(rhs)
+    }
+}
+
+impl BitOr<Square> for BitBoard {
+    type Output = Self;
+    #[inline(always)]
+    fn bitor(self, rhs: Square) -> Self::Output {
+        self | BitBoard::from(rhs)
+    }
+}
+
+impl BitXor<Square> for BitBoard {
+    type Output = Self;
+    #[inline(always)]
+    fn bitxor(self, rhs: Square) -> Self::Output {
+        self ^ BitBoard::from(rhs)
+    }
+}
+
 
 impl Mul<MagicNum> for BitBoard {
     type Output = u64;
     #[inline(always)]
     fn mul(self, rhs: MagicNum) -> Self::Output {
-        self.0 * rhs.0
+        self.0.wrapping_mul(rhs.0)
     }
 }
 
@@ -38,26 +82,66 @@ impl BitBoard {
         self.0 ^= 1 << square.0;
     }
 
+    // one square towards a side of the board, squares that would wrap around to the other edge are dropped
+    #[inline(always)]
+    pub fn north(self) -> Self { Self(self.0 << 8) }
+    #[inline(always)]
+    pub fn south(self) -> Self { Self(self.0 >> 8) }
+    #[inline(always)]
+    pub fn east(self) -> Self { Self((self.0 & !H_FILE.0) << 1) }
+    #[inline(always)]
+    pub fn west(self) -> Self { Self((self.0 & !A_FILE.0) >> 1) }
+    #[inline(always)]
+    pub fn north_east(self) -> Self { Self((self.0 & !H_FILE.0) << 9) }
+    #[inline(always)]
+    pub fn north_west(self) -> Self { Self((self.0 & !A_FILE.0) << 7) }
+    #[inline(always)]
+    pub fn south_east(self) -> Self { Self((self.0 & !H_FILE.0) >> 7) }
     #[inline(always)]
-    pub fn shift_board_left(&mut self, shift: u8) {
-        self.0 = self.0 >> shift;
+    pub fn south_west(self) -> Self { Self((self.0 & !A_FILE.0) >> 9) }
+
+    // every square from each set square to the edge, the squares themselves included
+    pub fn north_fill(self) -> Self {
+        let mut fill = self.0;
+        fill |= fill << 8;
+        fill |= fill << 16;
+        fill |= fill << 32;
+        Self(fill)
     }
 
-    #[inline(always)]
-    pub fn shift_board_right(&mut self, shift: u8) {
-        self.0 = self.0 << shift;
+    pub fn south_fill(self) -> Self {
+        let mut fill = self.0;
+        fill |= fill >> 8;
+        fill |= fill >> 16;
+        fill |= fill >> 32;
+        Self(fill)
     }
 
-    #[inline(always)]
-    pub fn shift_board_up(&mut self, shift: u8) {
-        self.shift_board_right(shift * 8);
+    pub fn file_fill(self) -> Self { self.north_fill() | self.south_fill() }
+
+    pub fn file_of(square: Square) -> Self { Self(A_FILE.0 << square.get_file()) }
+
+    pub fn rank_of(square: Square) -> Self { Self(RANK1.0 << (square.get_rank() * 8)) }
+
+    pub fn adjacent_files(square: Square) -> Self {
+        let file = Self::file_of(square);
+        file.east() | file.west()
     }
 
+    // the squares strictly between two squares on one rank, file or diagonal, empty when they are not aligned
     #[inline(always)]
-    pub fn shift_board_down(&mut self, shift: u8) {
-        self.shift_board_left(shift * 8);
-    }
-    
+    pub fn between(first: Square, second: Square) -> Self { BETWEEN_TABLE[first.0 as usize][second.0 as usize] }
+
+    // the whole line through two aligned squares from edge to edge, empty when they are not aligned
+    #[inline(always)]
+    pub fn line(first: Square, second: Square) -> Self { LINE_TABLE[first.0 as usize][second.0 as usize] }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool { self.0 == 0 }
+
+    #[inline(always)]
+    pub fn more_than_one(&self) -> bool { self.0 & self.0.wrapping_sub(1) != 0 }
+
     #[inline(always)]
     pub fn count_ones(&self) -> u32 {
         self.0.count_ones()
@@ -72,6 +156,11 @@ impl BitBoard {
         Square(self.0.trailing_zeros() as u8)
     }
 
+    pub fn get_msb_index(&self) -> Square {
+        debug_assert_ne!(self.0, 0);
+        Square(63 - self.0.leading_zeros() as u8)
+    }
+
     #[inline(always)]
     pub fn pop_square(&mut self) -> Square {
         debug_assert_ne!(self.0, 0);
@@ -126,28 +215,148 @@ impl std::fmt::Display for BitBoard {
 }
 
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Square(pub u8);
 
 impl Square {   
+    pub fn new(file: File, rank: Rank) -> Self { Self(rank as u8 * 8 + file as u8) }
+
     #[inline(always)]
     pub fn get_file(&self) -> u8 {self.0 % 8}
     #[inline(always)]
     pub fn get_rank(&self) -> u8 {self.0 / 8}
 
+    // human form, E4
     pub fn get_name(&self) -> String{
         let files = ["A","B","C","D","E","F","G","H"];
         files[self.get_file() as usize].to_string() + &(self.get_rank() + 1).to_string()
     }
 
+    // UCI and FEN form, e4
+    pub fn get_uci_name(&self) -> String {
+        format!("{}{}", File::of(*self), Rank::of(*self))
+    }
+
+    // None instead of wrapping around an edge of the board
+    pub fn offset(&self, file_step: i8, rank_step: i8) -> Option<Square> {
+        let file = File::from_index(u8::try_from((self.get_file() as i8).checked_add(file_step)?).ok()?)?;
+        let rank = Rank::from_index(u8::try_from((self.get_rank() as i8).checked_add(rank_step)?).ok()?)?;
+        Some(Square::new(file, rank))
+    }
+
+    pub fn checked_add(&self, direction: Direction) -> Option<Square> {
+        let (file_step, rank_step) = direction.get_steps();
+        self.offset(file_step, rank_step)
+    }
+
+    // a1 <-> a8, the same square seen from the other side
+    pub fn flip_rank(&self) -> Square { Square(self.0 ^ 56) }
+
+    // a1 <-> h1
+    pub fn flip_file(&self) -> Square { Square(self.0 ^ 7) }
+
+    pub fn relative(&self, side: Color) -> Square {
+        if side == Color::White { *self } else { self.flip_rank() }
+    }
+
     #[inline(always)]
     pub fn create_squares(start: u8, end: u8) -> impl Iterator<Item=Square> {
         (start..end).map(Square)
     }
 }
+
+// {} gives the UCI name e4 and {:#} the human one E4
 impl std::fmt::Display for Square {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.get_name())
+        if f.alternate() { write!(f, "{}", self.get_name()) } else { write!(f, "{}", self.get_uci_name()) }
+    }
+}
+
+// e4 and E4 are both read
+impl FromStr for Square {
+    type Err = Error;
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if !name.is_char_boundary(1) || name.len() != 2 { return Err(Error::InvalidSquare(name.to_string())); }
+        let (file, rank) = name.split_at(1);
+        match (file.parse(), rank.parse()) {
+            (Ok(file), Ok(rank)) => Ok(Square::new(file, rank)),
+            _ => Err(Error::InvalidSquare(name.to_string())),
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum File {
+    A, B, C, D, E, F, G, H,
+}
+
+impl File {
+    pub const ALL: [File; 8] = [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H];
+
+    pub fn from_index(index: u8) -> Option<File> { File::ALL.get(index as usize).copied() }
+
+    pub fn of(square: Square) -> File { File::ALL[square.get_file() as usize] }
+
+    // a <-> h
+    pub fn mirror(&self) -> File { File::ALL[7 - *self as usize] }
+
+    pub fn get_bitboard(&self) -> BitBoard { BitBoard(A_FILE.0 << *self as u8) }
+}
+
+impl std::fmt::Display for File {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", (b'a' + *self as u8) as char)
+    }
+}
+
+impl FromStr for File {
+    type Err = Error;
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.as_bytes() {
+            [file @ (b'a'..=b'h' | b'A'..=b'H')] => Ok(File::ALL[(file.to_ascii_lowercase() - b'a') as usize]),
+            _ => Err(Error::InvalidFile(name.to_string())),
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rank {
+    First, Second, Third, Fourth, Fifth, Sixth, Seventh, Eighth,
+}
+
+impl Rank {
+    pub const ALL: [Rank; 8] = [Rank::First, Rank::Second, Rank::Third, Rank::Fourth, Rank::Fifth, Rank::Sixth, Rank::Seventh, Rank::Eighth];
+
+    pub fn from_index(index: u8) -> Option<Rank> { Rank::ALL.get(index as usize).copied() }
+
+    pub fn of(square: Square) -> Rank { Rank::ALL[square.get_rank() as usize] }
+
+    // 1 <-> 8
+    pub fn flip(&self) -> Rank { Rank::ALL[7 - *self as usize] }
+
+    // the rank as the side sees it, the black pawns start on their second rank
+    pub fn relative(&self, side: Color) -> Rank {
+        if side == Color::White { *self } else { self.flip() }
+    }
+
+    pub fn get_bitboard(&self) -> BitBoard { BitBoard(RANK1.0 << (*self as u8 * 8)) }
+}
+
+impl std::fmt::Display for Rank {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", *self as u8 + 1)
+    }
+}
+
+impl FromStr for Rank {
+    type Err = Error;
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.as_bytes() {
+            [rank @ b'1'..=b'8'] => Ok(Rank::ALL[(rank - b'1') as usize]),
+            _ => Err(Error::InvalidRank(name.to_string())),
+        }
     }
 }
 
@@ -155,7 +364,7 @@ impl Add<Direction> for Square {
     type Output = Square;
     #[inline(always)]
     fn add(self, rhs: Direction) -> Self::Output {
-        Square(self.0 + rhs.0)
+        Square(self.0.wrapping_add(rhs.0))
     }
 }
 
@@ -163,18 +372,28 @@ impl Add<Direction> for Square {
 #[derive(Clone, Copy)]
 pub struct Direction(pub u8);
 
+impl Direction {
+    // (file step, rank step) of the wrapped signed offset, file steps are read between -3 and 4 so the
+    // eight directions and the double pawn pushes come out right
+    pub fn get_steps(&self) -> (i8, i8) {
+        let offset = self.0 as i8;
+        let file_step = (offset % 8 + 11) % 8 - 3;
+        (file_step, (offset - file_step) / 8)
+    }
+}
+
 impl Add<Square> for Direction {
     type Output = Square;
     #[inline(always)]
     fn add(self, rhs: Square) -> Self::Output {
-        Square(self.0 + rhs.0)
+        Square(self.0.wrapping_add(rhs.0))
     }
 }
 impl Mul<u8> for Direction {
     type Output = Direction;
     #[inline(always)]
     fn mul(self, rhs: u8) -> Self::Output {
-        Direction(self.0 * rhs)
+        Direction(self.0.wrapping_mul(rhs))
     }
 }
 
@@ -185,11 +404,33 @@ pub enum Color {
     Black = 1,
 }
 
+impl FromStr for Color {
+    type Err = Error;
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "w" | "white" => Ok(Color::White),
+            "b" | "black" => Ok(Color::Black),
+            _ => Err(Error::InvalidColor(name.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", if *self == Color::White { "white" } else { "black" })
+    }
+}
+
 pub struct MagicNumGenerator(u32);
 impl MagicNumGenerator {
     pub fn new() -> Self {
        Self(1804289383)
     }
+
+    // xorshift never leaves zero, so a zero seed falls back to the default one
+    pub fn from_seed(seed: u32) -> Self {
+        if seed == 0 { Self::new() } else { Self(seed) }
+    }
     #[inline(always)]
     pub fn get_random_u32(&mut self) -> u32 {
         self.0 ^= self.0 << 13;
@@ -216,6 +457,7 @@ impl MagicNumGenerator {
 #[derive(Clone, Copy, Debug)]
 pub struct MagicNum(pub u64);
 
+#[derive(Clone, Copy)]
 pub struct ChessBoard<T>(pub [T; 64]);
 
 impl <T> ChessBoard<T> {
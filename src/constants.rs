@@ -96,6 +96,8 @@ pub mod board_constants {
     pub const ROOK_MAX_BLOCK_PERM:   usize = 4096;
     pub const BISHOP_MAX_BLOCK_PERM: usize = 512;
 
+    pub const START_POS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
     pub const EMPTY_BITBOARD:     BitBoard =   BitBoard(0);
     pub const A_FILE:             BitBoard =   BitBoard(0x0101010101010101);
     pub const B_FILE:             BitBoard =   BitBoard(0x0202020202020202);
@@ -144,7 +146,7 @@ pub mod board_constants {
             12, 11, 11, 11, 11, 11, 11, 12
             ])
         }
-    // these numbers generated by pieces::helper_functions::find_magic_number
+    // these numbers generated by the magics binary, magics verify checks them
     pub const fn create_rook_magics() -> ChessBoard<MagicNum> {
         ChessBoard([
             MagicNum(0x8A80104000800020), MagicNum(0xC40100040082000),  MagicNum(0x100102001000840),  MagicNum(0x1080041000080080), 
@@ -166,7 +168,7 @@ pub mod board_constants {
         ])
     }
     
-    // these numbers generated by pieces::helper_functions::find_magic_number
+    // these numbers generated by the magics binary, magics verify checks them
     pub const fn create_bishop_magics() -> ChessBoard<MagicNum> {
         ChessBoard([
             MagicNum(0x40040822862081),   MagicNum(0x10201A0200411402), MagicNum(0x81024288020C000),  MagicNum(0x1404640080008810), 
@@ -194,6 +196,7 @@ pub mod eveluation_constants {
     use crate::board_components::ChessBoard;
     use crate::pieces::pieces_controller::BoardSlots;
     
+    #[derive(Clone, Copy)]
     pub struct MaterialScores {
         pub pawn_score:   isize,
         pub king_score:   isize,
@@ -201,17 +204,12 @@ pub mod eveluation_constants {
         pub queen_score:  isize,
         pub bishop_score: isize,
         pub knight_score: isize,
-        pub white_pawn_square_score:   ChessBoard<isize>,
-        pub white_knight_square_score: ChessBoard<isize>,
-        pub white_bishop_square_score: ChessBoard<isize>,
-        pub white_rook_square_score:   ChessBoard<isize>,
-        pub white_king_square_score:   ChessBoard<isize>,
-
-        pub black_pawn_square_score:   ChessBoard<isize>,
-        pub black_knight_square_score: ChessBoard<isize>,
-        pub black_bishop_square_score: ChessBoard<isize>,
-        pub black_rook_square_score:   ChessBoard<isize>,
-        pub black_king_square_score:   ChessBoard<isize>,
+        pub pawn_square_score:   ChessBoard<isize>,
+        pub knight_square_score: ChessBoard<isize>,
+        pub bishop_square_score: ChessBoard<isize>,
+        pub rook_square_score:   ChessBoard<isize>,
+        // tables are from white's point of view, black reads them with the rank flipped
+        pub king_square_score:   ChessBoard<isize>,
     }
     impl MaterialScores {
         pub const fn new() -> Self {
@@ -223,7 +221,7 @@ pub mod eveluation_constants {
                 knight_score: 300,
                 bishop_score: 320,
                 
-                white_pawn_square_score: ChessBoard([
+                pawn_square_score: ChessBoard([
                     0,   0,   0,   0,   0,   0,   0,   0,
                     0,   0,   0, -10, -10,   0,   0,   0,
                     0,   5,   0,   5,   5,   0,   5,   0,
@@ -234,7 +232,7 @@ pub mod eveluation_constants {
                     90,  90,  90,  90,  90,  90,  90,  90,
                 ]), 
 
-                white_knight_square_score: ChessBoard([
+                knight_square_score: ChessBoard([
                     -5, -10,   0,   0,   0,   0, -10,  -5,
                     -5,   0,   0,   0,   0,   0,   0,  -5,
                     -5,   5,  20,  10,  10,  20,   5,  -5,
@@ -245,7 +243,7 @@ pub mod eveluation_constants {
                     -5,   0,   0,   0,   0,   0,   0,  -5,
                 ]),
 
-                white_bishop_square_score: ChessBoard([
+                bishop_square_score: ChessBoard([
                     0,   0, -10,   0,   0, -10,   0,   0,
                     0,  30,   0,   0,   0,   0,  30,   0,
                     0,  10,   0,   0,   0,   0,  10,   0,
@@ -256,7 +254,7 @@ pub mod eveluation_constants {
                     0,   0,   0,   0,   0,   0,   0,   0,
                 ]),
 
-                white_rook_square_score: ChessBoard([
+                rook_square_score: ChessBoard([
                      0,   0,  0,   10,  10,  10,  0,   0,
                     -10,  0,  10,  20,  20,  10,  0,  -10,
                     -10,  0,  10,  20,  20,  10,  0,  -10,
@@ -267,7 +265,7 @@ pub mod eveluation_constants {
                      50,  50, 50,  50,  50,  50,  50,  50,
                 ]),  
                 
-                white_king_square_score: ChessBoard([
+                king_square_score: ChessBoard([
                     0,   0,   10,  0,   0,   0,   10,  0,
                     0,   0,   0,   0,   0,   0,   0,   0,
                     0,   0,   5,   10,  10,  5,   0,   0,
@@ -277,63 +275,111 @@ pub mod eveluation_constants {
                     0,   0,   5,   5,   5,   5,   0,   0,
                     0,   0,   0,   0,   0,   0,   0,   0,
                 ]),
+            }
+        }
+    }
 
-                black_pawn_square_score: ChessBoard([
-                    -90,  -90,  -90,  -90,  -90,  -90,  -90,  -90,
-                    -30,  -30,  -30,  -40,  -40,  -30,  -30,  -30,
-                    -20,  -20,  -20,  -30,  -30,  -30,  -20,  -20,
-                    -10,  -10,  -10,  -20,  -20,  -10,  -10,  -10,
-                    -5,    0,   -10,  -20,  -20,  -5,    0,   -5,
-                     0,   -5,    0,   -5,   -5,    0,   -5,    0,
-                     0,    0,    0,   10,    10,   0,    0,    0,
-                     0,    0,    0,   0,     0,    0,    0,    0]), 
-
-                black_knight_square_score: ChessBoard([
-                    5,   0,   0,     0,    0,   0,   0,  5,
-                    5,   0,   0,    -10,  -10,  0,   0,  5,
-                    5,   -5,  -20,  -20,  -20, -20, -5,  5,
-                    5,   -10, -20,  -30,  -30, -20, -10, 5,
-                    5,   -10, -20,  -30,  -30, -20, -10, 5,
-                    5,   -5,  -20,  -10,  -10, -20, -5,  5,
-                    5,    0,   0,    0,    0,   0,   0,  5,
-                    5,   10,   0,    0,    0,   0,   10, 5
-                ]), 
-                black_bishop_square_score: ChessBoard([
-                    0,   0,   0,   0,   0,   0,   0,   0,
-                    0,   0,   0,   0,   0,   0,   0,   0,
-                    0,   0,   0,  -10, -10,  0,   0,   0,
-                    0,   0,  -10, -20, -20, -10,  0,   0,
-                    0,   0,  -10, -20, -20, -10,  0,   0,
-                    0,  -10,  0,   0,   0,   0,  -10,  0,
-                    0,  -30,  0,   0,   0,   0,  -30,  0,
-                    0,   0,   10,  0,   0,   10,  0,   0
-                ]), 
-                black_rook_square_score: ChessBoard([
-                    -50,  -50,  -50,  -50,  -50,  -50,  -50,  -50,
-                    -50,  -50,  -50,  -50,  -50,  -50,  -50,  -50,
-                     0,    0,   -10,  -20,  -20,  -10,   0,    0,
-                     0,    0,   -10,  -20,  -20,  -10,   0,    0,
-                     0,    0,   -10,  -20,  -20,  -10,   0,    0,
-                     10,   0,   -10,  -20,  -20,  -10,   0,    10,
-                     10,   0,   -10,  -20,  -20,  -10,   0,    10,
-                     0,    0,    0,   -10,  -10,  -10,   0,    0
-                ]),  
-                black_king_square_score: ChessBoard([
-                    0,   0,   0,   0,   0,   0,   0,   0,
-                    0,   0,   0,   0,   0,   0,   0,   0,
-                    0,  -5,  -5,  -10, -10, -5,  -5,   0,
-                    0,  -5,  -10, -20, -20, -10, -5,   0,
-                    0,  -5,  -10, -20, -20, -10, -5,   0,
-                    0,   0,  -5,  -10, -10, -5,   0,   0,
-                    0,  -5,  -5,   5,   5,   0,  -5,   0,
-                    0,   0,  -10,  0,   0,   0,  -10,  0
-                ]),
+    #[derive(Clone, Copy, Debug, PartialEq, Default)]
+    pub struct TaperedScore {
+        pub mg: isize,
+        pub eg: isize,
+    }
+
+    impl TaperedScore {
+        pub const fn new(mg: isize, eg: isize) -> Self { Self { mg, eg } }
+
+        // phase goes from 0 (bare kings and pawns) up to MAX_PHASE (all pieces on the board)
+        #[inline(always)]
+        pub fn taper(&self, phase: isize) -> isize {
+            (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+        }
+    }
+
+    impl std::ops::Add for TaperedScore {
+        type Output = Self;
+        #[inline(always)]
+        fn add(self, rhs: Self) -> Self::Output { Self::new(self.mg + rhs.mg, self.eg + rhs.eg) }
+    }
+
+    impl std::ops::Sub for TaperedScore {
+        type Output = Self;
+        #[inline(always)]
+        fn sub(self, rhs: Self) -> Self::Output { Self::new(self.mg - rhs.mg, self.eg - rhs.eg) }
+    }
+
+    impl std::ops::Mul<isize> for TaperedScore {
+        type Output = Self;
+        #[inline(always)]
+        fn mul(self, rhs: isize) -> Self::Output { Self::new(self.mg * rhs, self.eg * rhs) }
+    }
+
+    impl std::ops::AddAssign for TaperedScore {
+        #[inline(always)]
+        fn add_assign(&mut self, rhs: Self) { *self = *self + rhs; }
+    }
 
+    const fn s(mg: isize, eg: isize) -> TaperedScore { TaperedScore::new(mg, eg) }
+
+    // Pawn Knight Bishop Rook Queen King
+    pub const PHASE_WEIGHTS: [isize; 6] = [0, 1, 1, 2, 4, 0];
+    pub const MAX_PHASE: isize = 24;
+
+    #[derive(Clone, Copy)]
+    pub struct PositionalScores {
+        // indexed by the number of safe squares the piece can move to
+        pub knight_mobility: [TaperedScore; 9],
+        pub bishop_mobility: [TaperedScore; 14],
+        pub rook_mobility:   [TaperedScore; 15],
+        pub queen_mobility:  [TaperedScore; 28],
+
+        // Pawn Knight Bishop Rook Queen King
+        pub king_attack_units: [isize; 6],
+        // applied as units * units * scale / 16 once at least two pieces attack the king zone
+        pub king_attack_scale: TaperedScore,
+        pub king_attack_max_units: isize,
+        // own pawn one square, two squares in front of the king or no pawn at all on the file
+        pub pawn_shield: [TaperedScore; 3],
+        // enemy pawn 1, 2, 3 or 4 ranks in front of the king
+        pub pawn_storm: [TaperedScore; 4],
+        pub king_semi_open_file: TaperedScore,
+        pub king_open_file: TaperedScore,
+    }
+
+    impl PositionalScores {
+        pub const fn new() -> Self {
+            Self {
+                knight_mobility: [
+                    s(-25, -30), s(-15, -20), s(-5, -10), s(0, -3), s(4, 3), s(8, 8), s(12, 11), s(15, 14), s(18, 16),
+                ],
+                bishop_mobility: [
+                    s(-25, -35), s(-12, -20), s(0, -8), s(5, 0), s(10, 6), s(15, 12), s(19, 17),
+                    s(22, 20), s(24, 23), s(26, 25), s(28, 27), s(30, 28), s(32, 30), s(34, 32),
+                ],
+                rook_mobility: [
+                    s(-30, -40), s(-15, -20), s(-5, -8), s(-3, 2), s(-1, 8), s(2, 14), s(5, 20), s(8, 26),
+                    s(11, 30), s(13, 34), s(15, 38), s(17, 41), s(18, 44), s(19, 46), s(20, 48),
+                ],
+                queen_mobility: [
+                    s(-15, -25), s(-10, -18), s(-5, -10), s(-2, -4), s(0, 0),   s(2, 4),   s(4, 8),
+                    s(6, 11),    s(8, 14),    s(9, 17),   s(10, 20), s(11, 22), s(12, 24), s(13, 26),
+                    s(14, 28),   s(15, 29),   s(16, 30),  s(17, 31), s(18, 32), s(19, 33), s(20, 34),
+                    s(21, 35),   s(22, 36),   s(23, 37),  s(24, 38), s(25, 39), s(26, 40), s(27, 41),
+                ],
+
+                king_attack_units:     [0, 2, 2, 3, 5, 0],
+                king_attack_scale:     s(-3, -1),
+                king_attack_max_units: 30,
+                pawn_shield:           [s(12, 0), s(6, 0), s(-12, -2)],
+                pawn_storm:            [s(-5, 0), s(-20, -2), s(-12, 0), s(-6, 0)],
+                king_semi_open_file:   s(-15, 0),
+                king_open_file:        s(-25, -5),
             }
         }
     }
-    
-    pub static MATERIAL_SCORES: MaterialScores = MaterialScores::new();
+
+    impl Default for PositionalScores {
+        fn default() -> Self { Self::new() }
+    }
 
     pub struct MmvLva([[u64; 12]; 12]);
 
@@ -3,10 +3,10 @@ use crate::board_components::{BitBoard, Square, Color};
 use crate::constants::directions::SOUTH;
 use crate::constants::{squares::{A8, H1, NO_SQUARE}, directions::*};
 use crate::pieces::pieces_controller::{BoardStatus, BoardSlots, CastleSlots, Castles, MoveList};
-use crate::constants::board_constants::{UNICODE_PIECES, ASCII_PIECES, H_FILE};
-use std::collections::VecDeque;
-use std::collections::HashMap;
-use std::env;
+use crate::constants::board_constants::{ASCII_PIECES, H_FILE};
+use crate::perft::{perft_divide, print_perft};
+use crate::render::{BoardRenderer, RenderStyle};
+use crate::san::legal_moves;
 
 pub fn str_to_piece(asci_piece: &str) -> BoardSlots {
     match asci_piece {
@@ -36,6 +36,14 @@ pub struct FenString {
     full_move: String,
 }
 
+// pieces of one rank from the a file on, '.' for empty squares
+fn expand_rank(rank: &str) -> Vec<char> {
+    rank.chars().flat_map(|c| match c.to_digit(10) {
+        Some(empty) => vec!['.'; empty as usize],
+        None        => vec![c],
+    }).collect()
+}
+
 impl FenString {
     pub fn new(fen: String) -> Self { 
         let data: Vec<&str> = fen.trim().split(" ").collect();
@@ -49,12 +57,56 @@ impl FenString {
         }
     }
     
-    pub fn from_board(board_status: &BoardStatus) -> Self {
-        let mut board_data = ["."; 64];
-        for piece in BoardSlots::iterate_pieces() {
-            for square in board_status[piece] {
-                board_data[square.0 as usize] = ASCII_PIECES[piece as usize];
+    // checks every field so convert_to_board can not panic, the move counters may be left out
+    pub fn parse(fen: &str) -> Result<Self, String> {
+        let data: Vec<&str> = fen.split_whitespace().collect();
+        if data.len() != 4 && data.len() != 6 { return Err(format!("fen needs 4 or 6 fields: {}", fen)); }
+        let ranks: Vec<&str> = data[0].split('/').collect();
+        if ranks.len() != 8 { return Err(format!("fen needs 8 ranks: {}", data[0])); }
+        for (index, rank) in ranks.iter().enumerate() {
+            let mut files = 0;
+            for c in rank.chars() {
+                match c {
+                    '1'..='8' => files += c.to_digit(10).unwrap(),
+                    'P' | 'p' if index == 0 || index == 7 => return Err(format!("pawn on the last rank: {}", data[0])),
+                    'P' | 'N' | 'B' | 'R' | 'Q' | 'K' | 'p' | 'n' | 'b' | 'r' | 'q' | 'k' => files += 1,
+                    _ => return Err(format!("invalid piece {} in {}", c, data[0])),
+                }
             }
+            if files != 8 { return Err(format!("rank {} does not have 8 files: {}", 8 - index, rank)); }
+        }
+        if data[0].matches('K').count() != 1 || data[0].matches('k').count() != 1 { return Err(format!("fen needs one king per side: {}", data[0])); }
+        if data[1] != "w" && data[1] != "b" { return Err(format!("invalid color {}", data[1])); }
+        if data[2] != "-" && !data[2].chars().all(|c| "KQkqABCDEFGHabcdefgh".contains(c)) { return Err(format!("invalid castles {}", data[2])); }
+        // every castling right needs its king and a rook of the same color on their back rank
+        let back_ranks = [expand_rank(ranks[7]), expand_rank(ranks[0])];
+        for c in data[2].chars().filter(|c| *c != '-') {
+            let (back_rank, king, rook) = if c.is_ascii_uppercase() { (&back_ranks[0], 'K', 'R') } else { (&back_ranks[1], 'k', 'r') };
+            let Some(king_file) = back_rank.iter().position(|piece| *piece == king) else { return Err(format!("castles {} without the king on its back rank", c)) };
+            let has_rook = match c.to_ascii_lowercase() {
+                'k'  => back_rank[king_file + 1..].contains(&rook),
+                'q'  => back_rank[..king_file].contains(&rook),
+                file => back_rank[(file as u8 - b'a') as usize] == rook,
+            };
+            if !has_rook { return Err(format!("castles {} without a rook to castle with", c)); }
+        }
+        let is_square = |name: &str| name.len() == 2 && ("a"..="h").contains(&&name[0..1]) && (name[1..2] == *"3" || name[1..2] == *"6");
+        if data[3] != "-" && !is_square(data[3]) { return Err(format!("invalid enpassant square {}", data[3])); }
+        let enpassant_rank = if data[1] == "w" { "6" } else { "3" };
+        if data[3] != "-" && data[3][1..] != *enpassant_rank { return Err(format!("enpassant square {} does not fit the side to move", data[3])); }
+        let (half_move, full_move) = if data.len() == 6 { (data[4], data[5]) } else { ("0", "1") };
+        if half_move.parse::<usize>().is_err() || full_move.parse::<usize>().is_err() { return Err(format!("invalid move counters {} {}", half_move, full_move)); }
+        Ok(Self::new(format!("{} {} {} {} {} {}", data[0], data[1], data[2], data[3], half_move, full_move)))
+    }
+
+    pub fn from_board(board_status: &BoardStatus) -> Self { Self::from_board_with(board_status, false) }
+
+    // castling rights are written as X-FEN (KQkq, a file letter only when the rook is not the outermost one)
+    // or as Shredder-FEN (always the rook file)
+    pub fn from_board_with(board_status: &BoardStatus, shredder: bool) -> Self {
+        let mut board_data = ["."; 64];
+        for square in Square::create_squares(0, 64) {
+            if let Some(piece) = board_status.piece_on(square) { board_data[square.0 as usize] = ASCII_PIECES[piece as usize]; }
         }
         let mut counter = 0;
         let mut board: String = "".to_string();
@@ -87,17 +139,23 @@ impl FenString {
         };
         
         let mut castles = "".to_string();
-        let mut flag = true;
-        if board_status.can_castle(CastleSlots::WhiteKingSide)  {castles += "K"; flag = false;}
-        if board_status.can_castle(CastleSlots::WhiteQueenSide) {castles += "Q"; flag = false;}
-        if board_status.can_castle(CastleSlots::BlackKingSide)  {castles += "k"; flag = false;}
-        if board_status.can_castle(CastleSlots::BlackQueenSide) {castles += "q"; flag = false;}
-        if flag {castles += "-";}
+        for castle in CastleSlots::ALL {
+            if !board_status.can_castle(castle) { continue; }
+            let rook_square = board_status.get_castle_rook(castle);
+            let rook = if castle.get_index() < 2 { BoardSlots::WhiteRook } else { BoardSlots::BlackRook };
+            let outer_files = if castle.is_king_side() { rook_square.get_file() + 1..8 } else { 0..rook_square.get_file() };
+            let is_outermost = outer_files.into_iter().all(|file| !board_status[rook].is_square_set(Square(rook_square.0 - rook_square.get_file() + file)));
+            let letter = if shredder || !is_outermost { (b'a' + rook_square.get_file()) as char }
+                else if castle.is_king_side() { 'k' }
+                else { 'q' };
+            castles.push(if castle.get_index() < 2 { letter.to_ascii_uppercase() } else { letter });
+        }
+        if castles.is_empty() {castles += "-";}
         
         let mut enpassant = "-".to_string();
         let enpassant_square = board_status.get_enpassant();
         if enpassant_square != NO_SQUARE {
-            enpassant = enpassant_square.get_name().to_lowercase().to_string();
+            enpassant = enpassant_square.get_uci_name();
         }
         let half_move = board_status.get_half_move().to_string();
         let full_move = board_status.get_full_move().to_string();
@@ -111,7 +169,7 @@ impl FenString {
     pub fn convert_to_board(&self) -> BoardStatus {
         let mut enpassant_square = NO_SQUARE;
         if self.enpassant != "-" {
-            enpassant_square = Square::create_squares(0, 64).find(|num| num.get_name().to_lowercase() == self.enpassant).unwrap();
+            enpassant_square = Square::create_squares(0, 64).find(|num| num.get_uci_name() == self.enpassant).unwrap();
         }
         let color = if self.color == "w" {Color::White} else {Color::Black};
         let half_move: usize = self.half_move.parse().unwrap();
@@ -129,38 +187,38 @@ impl FenString {
             }
         }
         
-        if !self.castles.contains("K") {board.remove_castle(CastleSlots::WhiteKingSide);}
-        if !self.castles.contains("k") {board.remove_castle(CastleSlots::BlackKingSide);}
-        if !self.castles.contains("Q") {board.remove_castle(CastleSlots::WhiteQueenSide);}
-        if !self.castles.contains("q") {board.remove_castle(CastleSlots::BlackQueenSide);}
+        // KQkq pick the outermost rook on that side of the king, file letters (X-FEN and Shredder-FEN) name the rook directly
+        let mut granted = [false; 4];
+        for c in self.castles.chars() {
+            let is_white = c.is_ascii_uppercase();
+            let (king, rook, rank) = if is_white {(BoardSlots::WhiteKing, BoardSlots::WhiteRook, 0)} else {(BoardSlots::BlackKing, BoardSlots::BlackRook, 7)};
+            let king_file = board[king].get_lsb_index().get_file();
+            let rook_file = match c.to_ascii_lowercase() {
+                'k'       => (king_file + 1..8).rev().find(|file| board[rook].is_square_set(Square(rank * 8 + file))),
+                'q'       => (0..king_file).find(|file| board[rook].is_square_set(Square(rank * 8 + file))),
+                'a'..='h' => Some(c.to_ascii_lowercase() as u8 - b'a'),
+                _         => None,
+            };
+            let Some(rook_file) = rook_file else { continue };
+            let castle = match (is_white, rook_file > king_file) {
+                (true, true)   => CastleSlots::WhiteKingSide,
+                (true, false)  => CastleSlots::WhiteQueenSide,
+                (false, true)  => CastleSlots::BlackKingSide,
+                (false, false) => CastleSlots::BlackQueenSide,
+            };
+            board.set_castle_rook(castle, Square(rank * 8 + rook_file));
+            granted[castle.get_index()] = true;
+        }
+        for castle in CastleSlots::ALL {
+            if !granted[castle.get_index()] { board.remove_castle(castle); }
+        }
         board
     }
     
     pub fn adjust_board_display(&self) -> String {
-        let mut data = ['.'; 64];
-        let mut square = 56; // A8
-        for c in self.board.chars() {
-            if c.is_numeric() {
-                for _ in 0..c.to_digit(10).unwrap() {
-                    data[square] = '.';
-                    square += 1;
-                }
-            }
-            else if c == '/' {square -= 16;} // below rank first square A1, A2 .. A7 
-            else {
-                data[square] = c;
-                square += 1;
-            }
-        }
-        let mut res = BitBoard::get_bitboard_string(data);
-        for &s in &ASCII_PIECES {
-            let piece = str_to_piece(s);
-            let unicode = UNICODE_PIECES[piece as usize];
-            res = res.replace(s, &unicode.to_string());
-        }
-        res = res.chars().enumerate().map(|data| if data.0 != 459 {data.1} else {'B'}).collect();
-        res = format!("\n----------------------------------------------------------------\nColor: {}, Castles: {}, Enpassant: {}, Half_move: {} Full_move: {}\n{}\n{}", self.color, self.castles, self.enpassant, self.half_move, self.full_move, self.get_fen_string(), res);
-        res
+        let board = BoardRenderer::new().set_style(RenderStyle::Unicode).render_text(&self.convert_to_board());
+        format!("\n----------------------------------------------------------------\nColor: {}, Castles: {}, Enpassant: {}, Half_move: {} Full_move: {}\n{}\n\n{}",
+            self.color, self.castles, self.enpassant, self.half_move, self.full_move, self.get_fen_string(), board)
     }
 }
 
@@ -170,17 +228,29 @@ impl std::fmt::Display for FenString {
     }
 }
 
-pub fn perft_diff_terminal() {
-    // example usage !!! DO NOT FORGET writing "" in fen string
-    // ./perft_diff.sh 4 "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1" 
-    // it means depth 4, fen = "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
-    // also you can use optional moves for example
-    // ./perft_diff.sh 4 "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" a2a3 a7a6
-    let args: Vec<String> = env::args().collect();
-    let depth: usize = args[1].parse().unwrap();
-    let fen: String = args[2].clone();
-    let moves: Vec<&str> = args[3].split(" ").collect();
-    perft_diff_manuel(fen, depth, moves, false);
+// the d command, the board with its fen followed by what the search sees of the position
+pub fn display_position(board: &BoardStatus, chess960: bool) -> String {
+    let checkers: Vec<String> = board.get_checkers().map(|square| square.to_string()).collect();
+    let moves: Vec<String> = legal_moves(board).iter().map(|mov| mov.get_uci_name(chess960)).collect();
+    format!("{}Key: {:016X}\nSide to move: {}\nCheckers: {}\nLegal moves ({}): {}",
+        board, board.get_hash(), board.get_color(), if checkers.is_empty() { "-".to_string() } else { checkers.join(" ") }, moves.len(), moves.join(" "))
+}
+
+// perftree protocol (https://github.com/agausmann/perftree): <depth> <fen> [moves], prints "move count" lines,
+// a blank line and the total. perftree passes the moves as one argument, separate arguments work as well
+// ./perft_diff.sh 4 "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" "a2a3 a7a6"
+pub fn perft_diff_terminal(args: &[String]) -> Result<usize, String> {
+    let depth: usize = args.first().and_then(|depth| depth.parse().ok()).ok_or("perft needs a depth")?;
+    let fen = args.get(1).ok_or("perft needs a fen")?;
+    let mut board = FenString::new(fen.clone()).convert_to_board();
+    for name in args.iter().skip(2).flat_map(|moves| moves.split_whitespace()) {
+        let mov = MoveList::generate(&board).iterate_moves().find(|mov| mov.get_move_name() == name);
+        mov.and_then(|mov| board.make_move(mov)).ok_or(format!("illegal move {}", name))?;
+    }
+    let result = perft_divide(&board, depth, 1, None, false);
+    for (name, nodes) in &result.divide { println!("{} {}", name, nodes); }
+    println!("\n{}", result.nodes);
+    Ok(result.nodes as usize)
 }
 
 pub fn perft_diff_manuel(fen: String, depth: usize, moves: Vec<&str>, print_moves: bool) {
@@ -210,38 +280,9 @@ pub fn perft_diff_manuel(fen: String, depth: usize, moves: Vec<&str>, print_move
 }
 
 pub fn perft_driver(uci_info: &UciInformation) -> usize {
-    let mut dq: VecDeque<(BoardStatus, String)> = VecDeque::new();
-    let mut parents: HashMap<String, usize> = HashMap::new();
-    let move_list = MoveList::new(uci_info);
-    for mov in move_list.iterate_moves() {
-        let mut copy_node: BoardStatus = uci_info.board;
-        if copy_node.make_move(mov) {
-            let key = mov.get_move_name();
-            parents.insert(key.clone(), 0);
-            dq.push_back((copy_node, key));
-        }
-    }
-    for _ in 0..(uci_info.depth_limit -1) {
-        let len = dq.len();
-        for _ in 0..len {
-            let node = dq.pop_front().unwrap();
-            let move_list = MoveList::new(&UciInformation::new().set_board(node.0));
-            move_list.iterate_moves().for_each(|mov| {
-                let mut copy_node = node.clone();
-                if copy_node.0.make_move(mov) {
-                    dq.push_back(copy_node);
-                }
-            });
-        }
-    }
-    for el in &dq {
-        *parents.get_mut(&el.1).unwrap() += 1;
-    }
-    for el in parents {
-        println!("{}: {}", el.0, el.1)
-    }
-    println!("\nNodes searched: {}", dq.len());
-    dq.len()
+    let result = perft_divide(&uci_info.board, uci_info.depth_limit as usize, 1, None, uci_info.chess960);
+    print_perft(&result);
+    result.nodes as usize
 }
 
 
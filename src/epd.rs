@@ -0,0 +1,282 @@
+use crate::debug::FenString;
+use crate::eveluation::{find_best_move, MATE_SCORE};
+use crate::eveluation_params::EvalParams;
+use crate::nnue::NnueState;
+use crate::pieces::pieces_controller::{BoardHistory, BoardStatus, MoveBitField};
+use crate::san::{move_to_san, san_to_move};
+use crate::uci::UciInformation;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+// EPD lines are the first four FEN fields followed by operations, each one an opcode with its
+// operands and a closing ';'. hmvc and fmvn give the move counters, moves are written in SAN.
+//   r1b1kb1r/3q1ppp/pBp1pn2/8/Np3P2/5B2/PPP3PP/R2Q1RK1 w kq - bm Bd4; id "WAC.232";
+pub struct EpdEntry {
+    pub board:      BoardStatus,
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+// operations that hold free text, their operand is written back in quotes
+fn is_string_opcode(opcode: &str) -> bool {
+    opcode == "id" || (opcode.len() == 2 && opcode.starts_with('c') && opcode.as_bytes()[1].is_ascii_digit())
+}
+
+fn parse_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut operations = Vec::new();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut token = String::new();
+    let mut in_quotes = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                if in_quotes { tokens.push(std::mem::take(&mut token)); }
+                in_quotes = !in_quotes;
+            },
+            _ if in_quotes => token.push(c),
+            ';' | ' ' | '\t' => {
+                if !token.is_empty() { tokens.push(std::mem::take(&mut token)); }
+                if c == ';' && !tokens.is_empty() {
+                    let opcode = tokens.remove(0);
+                    operations.push((opcode, std::mem::take(&mut tokens)));
+                }
+            },
+            _ => token.push(c),
+        }
+    }
+    if in_quotes { return Err(format!("unterminated string in {}", text)); }
+    if !token.is_empty() || !tokens.is_empty() { return Err(format!("operation without ';' in {}", text)); }
+    Ok(operations)
+}
+
+impl EpdEntry {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut rest = line.trim();
+        let mut fields = Vec::new();
+        for _ in 0..4 {
+            let (field, remaining) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            if field.is_empty() { return Err(format!("not enough fields in {}", line)); }
+            fields.push(field);
+            rest = remaining.trim_start();
+        }
+        let operations = parse_operations(rest)?;
+        let mut entry = Self { board: BoardStatus::new(), operations };
+        let counter = |opcode| entry.get(opcode).and_then(|operands| operands.first()).map_or("", String::as_str);
+        let half_move = if counter("hmvc").is_empty() { "0" } else { counter("hmvc") };
+        let full_move = if counter("fmvn").is_empty() { "1" } else { counter("fmvn") };
+        if half_move.parse::<usize>().is_err() || full_move.parse::<usize>().is_err() { return Err(format!("invalid move counters in {}", line)); }
+        entry.board = FenString::new(format!("{} {} {}", fields.join(" "), half_move, full_move)).convert_to_board();
+        Ok(entry)
+    }
+
+    pub fn get(&self, opcode: &str) -> Option<&Vec<String>> {
+        self.operations.iter().find(|(name, _)| name == opcode).map(|(_, operands)| operands)
+    }
+
+    pub fn set(&mut self, opcode: &str, operands: Vec<String>) {
+        match self.operations.iter_mut().find(|(name, _)| name == opcode) {
+            Some((_, old)) => *old = operands,
+            None => self.operations.push((opcode.to_string(), operands)),
+        }
+    }
+
+    pub fn get_id(&self) -> Option<&str> {
+        self.get("id").and_then(|operands| operands.first()).map(String::as_str)
+    }
+
+    // bm and am hold SAN moves
+    pub fn get_moves(&self, opcode: &str) -> Result<Vec<MoveBitField>, String> {
+        self.get(opcode).map_or(Ok(Vec::new()), |operands| operands.iter().map(|san| san_to_move(&self.board, san)).collect())
+    }
+
+    pub fn get_epd_string(&self) -> String {
+        let fen = FenString::from_board(&self.board).get_fen_string();
+        let mut epd = fen.split_whitespace().take(4).collect::<Vec<&str>>().join(" ");
+        for (opcode, operands) in &self.operations {
+            epd += " ";
+            epd += opcode;
+            for operand in operands {
+                if is_string_opcode(opcode) { epd += &format!(" \"{}\"", operand); } else { epd += &format!(" {}", operand); }
+            }
+            epd += ";";
+        }
+        epd
+    }
+}
+
+pub fn load_epd(path: &str) -> Result<Vec<EpdEntry>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("can not read {}: {}", path, err))?;
+    text.lines().filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#')).map(EpdEntry::parse).collect()
+}
+
+pub struct EpdLimits {
+    pub depth:    Option<isize>,
+    pub nodes:    Option<usize>,
+    pub movetime: Option<usize>,
+}
+
+const DEFAULT_DEPTH: isize = 5;
+// a time or node limit alone searches as deep as the limit allows
+const UNLIMITED_DEPTH: isize = 64;
+
+impl EpdLimits {
+    pub fn new() -> Self { Self { depth: None, nodes: None, movetime: None } }
+
+    pub fn set_depth(mut self, depth: isize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+    pub fn set_nodes(mut self, nodes: usize) -> Self {
+        self.nodes = Some(nodes);
+        self
+    }
+    pub fn set_movetime(mut self, movetime: usize) -> Self {
+        self.movetime = Some(movetime);
+        self
+    }
+
+    pub fn get_depth(&self) -> isize {
+        match self.depth {
+            Some(depth) => depth,
+            None if self.nodes.is_some() || self.movetime.is_some() => UNLIMITED_DEPTH,
+            None => DEFAULT_DEPTH,
+        }
+    }
+}
+
+impl Default for EpdLimits {
+    fn default() -> Self { Self::new() }
+}
+
+pub struct EpdResult {
+    pub suite:     String,
+    pub id:        String,
+    pub best_move: String,
+    pub score:     isize,
+    pub depth:     isize,
+    pub nodes:     usize,
+    // None when the entry has no bm, am or dm to check
+    pub passed:    Option<bool>,
+}
+
+// moves to mate from the root, negative when the side to move is mated
+pub fn get_mate_moves(score: isize, depth: isize) -> Option<isize> {
+    if score.abs() < MATE_SCORE { return None; }
+    let plies = depth - (score.abs() - MATE_SCORE);
+    Some(if score > 0 { (plies + 1) / 2 } else { -(plies + 1) / 2 })
+}
+
+// the suite is the id without its number (WAC.001 -> WAC), entries without an id use the file name
+fn get_suite(id: Option<&str>, path: &str) -> String {
+    match id.and_then(|id| id.rsplit_once('.')) {
+        Some((suite, _)) => suite.to_string(),
+        None => Path::new(path).file_stem().map_or(path.to_string(), |stem| stem.to_string_lossy().to_string()),
+    }
+}
+
+fn check_entry(entry: &EpdEntry, best_move: MoveBitField, score: isize, depth: isize) -> Result<Option<bool>, String> {
+    let best_moves = entry.get_moves("bm")?;
+    let avoid_moves = entry.get_moves("am")?;
+    let direct_mate = match entry.get("dm").and_then(|operands| operands.first()) {
+        Some(moves) => Some(moves.parse::<isize>().map_err(|_| format!("invalid dm {}", moves))?),
+        None => None,
+    };
+    if best_moves.is_empty() && avoid_moves.is_empty() && direct_mate.is_none() { return Ok(None); }
+    let mut passed = best_move != MoveBitField::NO_MOVE;
+    if !best_moves.is_empty() { passed &= best_moves.contains(&best_move); }
+    if !avoid_moves.is_empty() { passed &= !avoid_moves.contains(&best_move); }
+    if let Some(moves) = direct_mate {
+        passed &= get_mate_moves(score, depth).is_some_and(|mate| mate > 0 && mate <= moves);
+    }
+    Ok(Some(passed))
+}
+
+// searches every position of the files, the results are added to the entries as acd, acn, ce and pm
+// and written to output when it is given
+pub fn run_epd(paths: &[&str], limits: &EpdLimits, eval_params: EvalParams, nnue: Option<NnueState>, output: Option<&str>) -> Result<Vec<EpdResult>, String> {
+    let mut writer = match output {
+        Some(path) => Some(BufWriter::new(File::create(path).map_err(|err| format!("can not create {}: {}", path, err))?)),
+        None => None,
+    };
+    let mut uci_info = UciInformation::new().set_eval_params(eval_params).set_nnue(nnue);
+    let mut results = Vec::new();
+    for path in paths {
+        for mut entry in load_epd(path)? {
+            uci_info.board = entry.board;
+            uci_info.depth_limit = limits.get_depth();
+            uci_info.nodes_limit = limits.nodes;
+            uci_info.time_limit = limits.movetime;
+            uci_info.node_count = 0;
+            uci_info.board_history = BoardHistory::new();
+            uci_info.start_time = Instant::now();
+            find_best_move(&mut uci_info);
+
+            let history = uci_info.board_history;
+            let best_move = history.found_best_move;
+            let best_san = if best_move == MoveBitField::NO_MOVE { "none".to_string() } else { move_to_san(&entry.board, best_move) };
+            // a bm or am the board can not play counts as a failure instead of stopping the run
+            let passed = check_entry(&entry, best_move, history.found_best_score, history.found_best_depth).unwrap_or_else(|err| {
+                println!("info string {} {}", entry.get_id().unwrap_or(path), err);
+                Some(false)
+            });
+            entry.set("acd", vec![history.found_best_depth.to_string()]);
+            entry.set("acn", vec![uci_info.node_count.to_string()]);
+            entry.set("ce", vec![history.found_best_score.to_string()]);
+            if best_move != MoveBitField::NO_MOVE { entry.set("pm", vec![best_san.clone()]); }
+            if let Some(writer) = &mut writer {
+                writeln!(writer, "{}", entry.get_epd_string()).map_err(|err| format!("can not write {}: {}", output.unwrap(), err))?;
+            }
+
+            let result = EpdResult {
+                suite:     get_suite(entry.get_id(), path),
+                id:        entry.get_id().unwrap_or("").to_string(),
+                best_move: best_san,
+                score:     history.found_best_score,
+                depth:     history.found_best_depth,
+                nodes:     uci_info.node_count,
+                passed,
+            };
+            print_epd_result(&result);
+            results.push(result);
+        }
+    }
+    if let Some(writer) = &mut writer {
+        writer.flush().map_err(|err| format!("can not write {}: {}", output.unwrap(), err))?;
+    }
+    Ok(results)
+}
+
+fn print_epd_result(result: &EpdResult) {
+    let status = match result.passed {
+        Some(true)  => "ok",
+        Some(false) => "FAILED",
+        None        => "-",
+    };
+    let score = match get_mate_moves(result.score, result.depth) {
+        Some(mate) => format!("mate {}", mate),
+        None => format!("cp {}", result.score),
+    };
+    println!("{} {} depth {} nodes {} {} {}", result.id, result.best_move, result.depth, result.nodes, score, status);
+}
+
+// suites are listed in the order they first appear
+pub fn print_epd_summary(results: &[EpdResult]) {
+    let mut suites: Vec<(&str, usize, usize)> = Vec::new();
+    for result in results.iter().filter(|result| result.passed.is_some()) {
+        let index = match suites.iter().position(|(suite, _, _)| *suite == result.suite) {
+            Some(index) => index,
+            None => { suites.push((&result.suite, 0, 0)); suites.len() - 1 },
+        };
+        suites[index].1 += result.passed.unwrap() as usize;
+        suites[index].2 += 1;
+    }
+    println!("\n{:<30} {:>8} {:>8} {:>8}", "Suite", "Passed", "Total", "Percent");
+    let (mut passed, mut total) = (0, 0);
+    for (suite, suite_passed, suite_total) in &suites {
+        println!("{:<30} {:>8} {:>8} {:>7.1}%", suite, suite_passed, suite_total, *suite_passed as f64 * 100.0 / *suite_total as f64);
+        passed += suite_passed;
+        total += suite_total;
+    }
+    if total > 0 { println!("{:<30} {:>8} {:>8} {:>7.1}%", "Total", passed, total, passed as f64 * 100.0 / total as f64); }
+}
@@ -1,56 +1,327 @@
 use crate::pieces::pieces_controller::{BoardSlots, BoardStatus, MoveList, MoveBitField};
-use crate::board_components::Color;
-use crate::constants::eveluation_constants::MATERIAL_SCORES;
+use crate::board_components::{BitBoard, Color, Square};
+use crate::constants::board_constants::{A_FILE, EMPTY_BITBOARD};
+use crate::constants::eveluation_constants::{PHASE_WEIGHTS, MAX_PHASE, TaperedScore};
+use crate::eveluation_params::EvalParams;
 use crate::pieces::pieces_controller::{is_square_attacked_black, is_square_attacked_white};
+use crate::pieces::tables::{generate_bishop_attacks, generate_king_attacks, generate_knight_attacks, generate_queen_attacks, generate_rook_attakcs, genereate_pawn_attacks};
 use crate::uci::UciInformation;
+use std::cmp::min;
+use std::mem::transmute;
 
 
+#[repr(usize)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EvalTerm {
+    Material    = 0,
+    PieceSquare = 1,
+    Mobility    = 2,
+    KingAttack  = 3,
+    KingShelter = 4,
+}
+
+impl EvalTerm {
+    pub const ALL: [EvalTerm; 5] = [EvalTerm::Material, EvalTerm::PieceSquare, EvalTerm::Mobility, EvalTerm::KingAttack, EvalTerm::KingShelter];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            EvalTerm::Material    => "Material",
+            EvalTerm::PieceSquare => "PST",
+            EvalTerm::Mobility    => "Mobility",
+            EvalTerm::KingAttack  => "King attack",
+            EvalTerm::KingShelter => "King shelter",
+        }
+    }
+}
+
+// search evaluates through NoTrace, record calls compile away so only the eval command pays for EvalTrace
+pub trait EvalTracer {
+    fn record(&mut self, term: EvalTerm, color: Color, score: TaperedScore);
+}
+
+pub struct NoTrace;
+
+impl EvalTracer for NoTrace {
+    #[inline(always)]
+    fn record(&mut self, _term: EvalTerm, _color: Color, _score: TaperedScore) {}
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EvalTrace {
+    pub terms: [[TaperedScore; 2]; 5],
+    pub phase: isize,
+    // tapered total from white's point of view
+    pub score: isize,
+}
+
+impl EvalTracer for EvalTrace {
+    fn record(&mut self, term: EvalTerm, color: Color, score: TaperedScore) {
+        self.terms[term as usize][color as usize] += score;
+    }
+}
+
+impl EvalTrace {
+    pub fn get_term(&self, term: EvalTerm, color: Color) -> TaperedScore {
+        self.terms[term as usize][color as usize]
+    }
+}
+
+impl std::fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "        Term    |     White     |     Black     |          Total")?;
+        writeln!(f, "                |    MG     EG  |    MG     EG  |    MG     EG  Tapered")?;
+        writeln!(f, " ---------------+---------------+---------------+----------------------")?;
+        let mut total = [TaperedScore::default(); 2];
+        for term in EvalTerm::ALL {
+            let white = self.get_term(term, Color::White);
+            let black = self.get_term(term, Color::Black);
+            let diff  = white - black;
+            total[0] += white;
+            total[1] += black;
+            writeln!(f, " {:>14} | {:>5}  {:>5}  | {:>5}  {:>5}  | {:>5}  {:>5}  {:>7}", term.get_name(),
+                white.mg, white.eg, black.mg, black.eg, diff.mg, diff.eg, diff.taper(self.phase))?;
+        }
+        let diff = total[0] - total[1];
+        writeln!(f, " ---------------+---------------+---------------+----------------------")?;
+        writeln!(f, " {:>14} | {:>5}  {:>5}  | {:>5}  {:>5}  | {:>5}  {:>5}  {:>7}", "Total",
+            total[0].mg, total[0].eg, total[1].mg, total[1].eg, diff.mg, diff.eg, diff.taper(self.phase))?;
+        writeln!(f, "\nPhase: {}/{}", self.phase, MAX_PHASE)?;
+        write!(f, "Final evaluation: {} (white side)", self.score)
+    }
+}
+
 #[inline(always)]
-pub fn eveluate(board_status: &BoardStatus) -> isize {
-    let mut score: isize = 0;
-    
-    for square in board_status[BoardSlots::WhitePawn]   {score += MATERIAL_SCORES.white_pawn_square_score[square];   score += MATERIAL_SCORES.pawn_score }   
-    for square in board_status[BoardSlots::WhiteBishop] {score += MATERIAL_SCORES.white_bishop_square_score[square]; score += MATERIAL_SCORES.bishop_score;}   
-    for square in board_status[BoardSlots::WhiteKing]   {score += MATERIAL_SCORES.white_king_square_score[square];}   
-    for square in board_status[BoardSlots::WhiteKnight] {score += MATERIAL_SCORES.white_knight_square_score[square]; score += MATERIAL_SCORES.knight_score;}   
-    for square in board_status[BoardSlots::WhiteRook]   {score += MATERIAL_SCORES.white_rook_square_score[square];   score += MATERIAL_SCORES.rook_score;}
-    for _ in board_status[BoardSlots::WhiteQueen]       {score += MATERIAL_SCORES.queen_score;}
-
-    for square in board_status[BoardSlots::BlackPawn]   {score += MATERIAL_SCORES.black_pawn_square_score[square];   score -= MATERIAL_SCORES.pawn_score;}   
-    for square in board_status[BoardSlots::BlackBishop] {score += MATERIAL_SCORES.black_bishop_square_score[square]; score -= MATERIAL_SCORES.bishop_score;}   
-    for square in board_status[BoardSlots::BlackKing]   {score += MATERIAL_SCORES.black_king_square_score[square];}   
-    for square in board_status[BoardSlots::BlackKnight] {score += MATERIAL_SCORES.black_knight_square_score[square]; score -= MATERIAL_SCORES.knight_score;}   
-    for square in board_status[BoardSlots::BlackRook]   {score += MATERIAL_SCORES.black_rook_square_score[square];   score -= MATERIAL_SCORES.rook_score;}
-    for _ in board_status[BoardSlots::BlackQueen]       {score -= MATERIAL_SCORES.queen_score;}
-    
+pub fn eveluate(board_status: &BoardStatus, params: &EvalParams) -> isize {
+    eveluate_with(board_status, params, &mut NoTrace)
+}
+
+pub fn trace_eveluate(board_status: &BoardStatus, params: &EvalParams) -> EvalTrace {
+    let mut trace = EvalTrace::default();
+    let score = eveluate_with(board_status, params, &mut trace);
+    trace.phase = game_phase(board_status);
+    trace.score = match board_status.get_color() {
+        Color::White => score,
+        Color::Black => -score,
+    };
+    trace
+}
+
+#[inline(always)]
+fn eveluate_with<T: EvalTracer>(board_status: &BoardStatus, params: &EvalParams, tracer: &mut T) -> isize {
+    let white = eveluate_side(board_status, params, Color::White, Color::Black, tracer);
+    let black = eveluate_side(board_status, params, Color::Black, Color::White, tracer);
+    let score = (white - black).taper(game_phase(board_status));
+
     match board_status.get_color() {
         Color::White => score,
         Color::Black => -score,
     }
 }
 
+// piece: 0 Pawn, 1 Knight, 2 Bishop, 3 Rook, 4 Queen, 5 King
+#[inline(always)]
+fn piece_slot(color: Color, piece: usize) -> BoardSlots {
+    unsafe { transmute((piece + 6 * color as usize) as u8) }
+}
+
+#[inline(always)]
+fn game_phase(board_status: &BoardStatus) -> isize {
+    let phase = (1..5).map(|piece| {
+        let count = board_status[piece_slot(Color::White, piece)].count_ones() + board_status[piece_slot(Color::Black, piece)].count_ones();
+        PHASE_WEIGHTS[piece] * count as isize
+    }).sum();
+    min(phase, MAX_PHASE)
+}
+
+#[inline(always)]
+fn eveluate_side<T: EvalTracer>(board_status: &BoardStatus, params: &EvalParams, color: Color, other_color: Color, tracer: &mut T) -> TaperedScore {
+    let (material, piece_square) = eveluate_material(board_status, params, color);
+    let mobility     = eveluate_mobility(board_status, params, color, other_color);
+    let king_attack  = eveluate_king_attack(board_status, params, color, other_color);
+    let king_shelter = eveluate_king_shelter(board_status, params, color, other_color);
+
+    tracer.record(EvalTerm::Material,    color, material);
+    tracer.record(EvalTerm::PieceSquare, color, piece_square);
+    tracer.record(EvalTerm::Mobility,    color, mobility);
+    tracer.record(EvalTerm::KingAttack,  color, king_attack);
+    tracer.record(EvalTerm::KingShelter, color, king_shelter);
+    material + piece_square + mobility + king_attack + king_shelter
+}
+
+// square tables are from white's point of view, black reads them mirrored
+#[inline(always)]
+fn eveluate_material(board_status: &BoardStatus, params: &EvalParams, color: Color) -> (TaperedScore, TaperedScore) {
+    let tables = &params.material;
+    let mut material = 0;
+    let mut piece_square = 0;
+
+    for square in board_status[piece_slot(color, 0)] {material += tables.pawn_score;   piece_square += tables.pawn_square_score[square.relative(color)];}
+    for square in board_status[piece_slot(color, 1)] {material += tables.knight_score; piece_square += tables.knight_square_score[square.relative(color)];}
+    for square in board_status[piece_slot(color, 2)] {material += tables.bishop_score; piece_square += tables.bishop_square_score[square.relative(color)];}
+    for square in board_status[piece_slot(color, 3)] {material += tables.rook_score;   piece_square += tables.rook_square_score[square.relative(color)];}
+    for _      in board_status[piece_slot(color, 4)] {material += tables.queen_score;}
+    for square in board_status[piece_slot(color, 5)] {piece_square += tables.king_square_score[square.relative(color)];}
+
+    (TaperedScore::new(material, material), TaperedScore::new(piece_square, piece_square))
+}
+
+#[inline(always)]
+fn pawn_attacks(board_status: &BoardStatus, color: Color) -> BitBoard {
+    let mut attacks = BitBoard::new();
+    for square in board_status[piece_slot(color, 0)] { attacks = attacks | genereate_pawn_attacks(square, color); }
+    attacks
+}
+
+#[inline(always)]
+fn eveluate_mobility(board_status: &BoardStatus, params: &EvalParams, color: Color, other_color: Color) -> TaperedScore {
+    let own_pieces = match color {
+        Color::White => BoardSlots::WhitePieces,
+        Color::Black => BoardSlots::BlackPieces,
+    };
+    let area = !(board_status[own_pieces] | pawn_attacks(board_status, other_color));
+    let occupancy = board_status[BoardSlots::AllPieces];
+    let mut score = TaperedScore::default();
+
+    for square in board_status[piece_slot(color, 1)] {
+        score += params.positional.knight_mobility[(generate_knight_attacks(square) & area).count_ones() as usize];
+    }
+    for square in board_status[piece_slot(color, 2)] {
+        score += params.positional.bishop_mobility[(generate_bishop_attacks(square, occupancy) & area).count_ones() as usize];
+    }
+    for square in board_status[piece_slot(color, 3)] {
+        score += params.positional.rook_mobility[(generate_rook_attakcs(square, occupancy) & area).count_ones() as usize];
+    }
+    for square in board_status[piece_slot(color, 4)] {
+        score += params.positional.queen_mobility[(generate_queen_attacks(square, occupancy) & area).count_ones() as usize];
+    }
+    score
+}
+
+// squares on the ranks in front of the square from the color's point of view
+#[inline(always)]
+fn in_front_mask(square: Square, color: Color) -> BitBoard {
+    let rank = square.get_rank() as u32;
+    match color {
+        Color::White => BitBoard(u64::MAX.checked_shl(8 * (rank + 1)).unwrap_or(0)),
+        Color::Black => BitBoard((1 << (8 * rank)) - 1),
+    }
+}
+
+// rank distance between the king and the closest pawn in front of it
+#[inline(always)]
+fn closest_pawn_distance(pawns: BitBoard, king_square: Square, color: Color) -> Option<u8> {
+    if pawns == EMPTY_BITBOARD { return None; }
+    match color {
+        Color::White => Some(pawns.get_lsb_index().get_rank() - king_square.get_rank()),
+        Color::Black => Some(king_square.get_rank() - Square(63 - pawns.0.leading_zeros() as u8).get_rank()),
+    }
+}
+
+#[inline(always)]
+fn eveluate_king_attack(board_status: &BoardStatus, params: &EvalParams, color: Color, other_color: Color) -> TaperedScore {
+    let king_square = board_status[piece_slot(color, 5)].get_lsb_index();
+    let mut king_zone = generate_king_attacks(king_square);
+    king_zone.set_bit(king_square);
+    let occupancy = board_status[BoardSlots::AllPieces];
+
+    let mut attack_units = 0;
+    let mut attackers = 0;
+    for piece in 1..5 {
+        for square in board_status[piece_slot(other_color, piece)] {
+            let attacks = match piece {
+                1 => generate_knight_attacks(square),
+                2 => generate_bishop_attacks(square, occupancy),
+                3 => generate_rook_attakcs(square, occupancy),
+                _ => generate_queen_attacks(square, occupancy),
+            };
+            if (attacks & king_zone) == EMPTY_BITBOARD { continue; }
+            attack_units += params.positional.king_attack_units[piece];
+            attackers += 1;
+        }
+    }
+    if attackers < 2 { return TaperedScore::default(); }
+    let units = min(attack_units, params.positional.king_attack_max_units);
+    let scale = params.positional.king_attack_scale;
+    TaperedScore::new(scale.mg * units * units / 16, scale.eg * units * units / 16)
+}
+
+// pawn shield, pawn storm and open files on the king file and its neighbours
+#[inline(always)]
+fn eveluate_king_shelter(board_status: &BoardStatus, params: &EvalParams, color: Color, other_color: Color) -> TaperedScore {
+    let king_square = board_status[piece_slot(color, 5)].get_lsb_index();
+    let mut score = TaperedScore::default();
+
+    let own_pawns   = board_status[piece_slot(color, 0)];
+    let enemy_pawns = board_status[piece_slot(other_color, 0)];
+    let in_front    = in_front_mask(king_square, color);
+    let king_file   = king_square.get_file();
+    for file in king_file.saturating_sub(1)..=min(king_file + 1, 7) {
+        let file_mask = BitBoard(A_FILE.0 << file);
+        let own_file_pawns   = own_pawns & file_mask;
+        let enemy_file_pawns = enemy_pawns & file_mask;
+        if own_file_pawns == EMPTY_BITBOARD {
+            if enemy_file_pawns == EMPTY_BITBOARD { score += params.positional.king_open_file; }
+            else { score += params.positional.king_semi_open_file; }
+        }
+        score += match closest_pawn_distance(own_file_pawns & in_front, king_square, color) {
+            Some(1) => params.positional.pawn_shield[0],
+            Some(2) => params.positional.pawn_shield[1],
+            _       => params.positional.pawn_shield[2],
+        };
+        if let Some(distance @ 1..=4) = closest_pawn_distance(enemy_file_pawns & in_front, king_square, color) {
+            score += params.positional.pawn_storm[distance as usize - 1];
+        }
+    }
+    score
+}
+
+#[inline(always)]
+fn eveluate_position(uci_info: &UciInformation) -> isize {
+    match &uci_info.nnue {
+        Some(nnue) => nnue.eveluate(uci_info.board.get_color()),
+        None       => eveluate(&uci_info.board, &uci_info.eval_params),
+    }
+}
+
+// a mate found with `depth` plies left scores MATE_SCORE + depth for the winning side
+pub const MATE_SCORE: isize = 50000;
+
+#[inline(always)]
+fn push_ply(uci_info: &mut UciInformation) {
+    uci_info.ply += 1;
+    if let Some(nnue) = &mut uci_info.nnue { nnue.push(&uci_info.board); }
+}
+
+#[inline(always)]
+fn pop_ply(uci_info: &mut UciInformation) {
+    uci_info.ply -= 1;
+    if let Some(nnue) = &mut uci_info.nnue { nnue.pop(); }
+}
 
 pub fn find_best_move(uci_info: &mut UciInformation) {
     if uci_info.depth_limit == 0 {return ;}
+    if let Some(nnue) = &mut uci_info.nnue { nnue.reset(&uci_info.board); }
+    uci_info.ply = 0;
     for depth in 1..uci_info.depth_limit + 1 {
-        uci_info.node_count = 1;
         let move_list = MoveList::new(uci_info);
         let mut best_move = MoveBitField::NO_MOVE;
         let beta = 1000000;
         let mut alpha = -1000000;
-        let old_board = uci_info.board;
         for mov in move_list.iterate_moves() {
-            if uci_info.board.make_move(mov) {
+            if let Some(undo) = uci_info.board.make_move(mov) {
+                push_ply(uci_info);
                 let score = -negamax(uci_info, -alpha, -beta, depth -1);
+                pop_ply(uci_info);
+                uci_info.board.unmake_move(mov, &undo);
                 if uci_info.is_search_fnished() {return;}
                 if score > alpha {
                     alpha = score;
                     best_move = mov;
                 }
             }
-            uci_info.board = old_board;
         }
-        uci_info.board_history.add_new_best_move(best_move);
+        uci_info.board_history.add_new_best_move(best_move, alpha, depth);
+        if let Some(report) = uci_info.report_iteration { report(uci_info); }
     }
 }
 
@@ -58,17 +329,18 @@ pub fn find_best_move(uci_info: &mut UciInformation) {
 fn quiescence(uci_info: &mut UciInformation, beta: isize, mut alpha: isize) -> isize {
     if uci_info.is_search_fnished() { return alpha; }
     uci_info.node_count += 1;
-    let stdpt = eveluate(&uci_info.board);
+    let stdpt = eveluate_position(uci_info);
     if stdpt >= beta {return beta}
     alpha = isize::max(alpha, stdpt);
-    let old_board = uci_info.board;
     for mov in MoveList::new(&uci_info).iterate_moves().filter(MoveBitField::is_move_capture) {
-        if uci_info.board.make_move(mov) {
+        if let Some(undo) = uci_info.board.make_move(mov) {
+            push_ply(uci_info);
             let score = -quiescence(uci_info, -alpha, -beta);
+            pop_ply(uci_info);
+            uci_info.board.unmake_move(mov, &undo);
             if score >= beta {return beta;}
             alpha = isize::max(alpha, score);
         }
-        uci_info.board = old_board;
     }   
     alpha
 }
@@ -80,25 +352,26 @@ fn negamax(uci_info: &mut UciInformation, beta: isize, mut alpha: isize, depth:
     uci_info.node_count += 1;
     let move_list = MoveList::new(&uci_info);
     let mut move_count = 0;
-    let old_board = uci_info.board;
     for mov in move_list.iterate_moves() {
-        if uci_info.board.make_move(mov) {
+        if let Some(undo) = uci_info.board.make_move(mov) {
+            push_ply(uci_info);
             let score = -negamax(uci_info, -alpha, -beta, depth - 1);
+            pop_ply(uci_info);
+            uci_info.board.unmake_move(mov, &undo);
             if score >= beta {
-                if !mov.is_move_capture() {uci_info.board_history.append_killer_move(mov, old_board.get_half_move());}
+                if !mov.is_move_capture() {uci_info.board_history.append_killer_move(mov, uci_info.ply);}
                 return beta;
             }
             alpha = isize::max(score, alpha);
             move_count += 1;
         }
-        uci_info.board = old_board;
     }
     if move_count == 0 {
         if uci_info.board.get_color() == Color::White {
-            if is_square_attacked_white(&uci_info.board, uci_info.board[BoardSlots::WhiteKing].get_lsb_index()) {return -50000 - depth;}
+            if is_square_attacked_white(&uci_info.board, uci_info.board[BoardSlots::WhiteKing].get_lsb_index()) {return -MATE_SCORE - depth;}
         }
         else {
-            if is_square_attacked_black(&uci_info.board, uci_info.board[BoardSlots::BlackKing].get_lsb_index()) {return -50000 - depth;}
+            if is_square_attacked_black(&uci_info.board, uci_info.board[BoardSlots::BlackKing].get_lsb_index()) {return -MATE_SCORE - depth;}
         }
         return 0;
     }
@@ -0,0 +1,145 @@
+use crate::board_components::{ChessBoard, Square};
+use crate::constants::eveluation_constants::{MaterialScores, PositionalScores, TaperedScore};
+use std::fs;
+
+// Every weight the evaluation reads, addressable by name so it can be
+// loaded from a file, changed with setoption or handed to the tuner.
+#[derive(Clone, Copy)]
+pub struct EvalParams {
+    pub material:   MaterialScores,
+    pub positional: PositionalScores,
+}
+
+impl EvalParams {
+    pub const fn new() -> Self {
+        Self { material: MaterialScores::new(), positional: PositionalScores::new() }
+    }
+
+    // names look like "pawn_score", "knight_square_score[e4]", "rook_mobility[3].eg",
+    // square tables are from white's point of view and black uses them mirrored, so tuning keeps the colors symmetric
+    pub fn visit_params(&mut self, mut visit: impl FnMut(String, &mut isize)) {
+        let material = &mut self.material;
+        visit("pawn_score".to_string(),   &mut material.pawn_score);
+        visit("knight_score".to_string(), &mut material.knight_score);
+        visit("bishop_score".to_string(), &mut material.bishop_score);
+        visit("rook_score".to_string(),   &mut material.rook_score);
+        visit("queen_score".to_string(),  &mut material.queen_score);
+
+        let tables = [
+            ("pawn_square_score",   &mut material.pawn_square_score),
+            ("knight_square_score", &mut material.knight_square_score),
+            ("bishop_square_score", &mut material.bishop_square_score),
+            ("rook_square_score",   &mut material.rook_square_score),
+            ("king_square_score",   &mut material.king_square_score),
+        ];
+        for (name, table) in tables { visit_square_table(name, table, &mut visit); }
+
+        let positional = &mut self.positional;
+        visit_scores("knight_mobility", &mut positional.knight_mobility, &mut visit);
+        visit_scores("bishop_mobility", &mut positional.bishop_mobility, &mut visit);
+        visit_scores("rook_mobility",   &mut positional.rook_mobility,   &mut visit);
+        visit_scores("queen_mobility",  &mut positional.queen_mobility,  &mut visit);
+
+        for (index, units) in positional.king_attack_units.iter_mut().enumerate() {
+            visit(format!("king_attack_units[{}]", index), units);
+        }
+        visit_score("king_attack_scale".to_string(), &mut positional.king_attack_scale, &mut visit);
+        visit("king_attack_max_units".to_string(), &mut positional.king_attack_max_units);
+        visit_scores("pawn_shield", &mut positional.pawn_shield, &mut visit);
+        visit_scores("pawn_storm",  &mut positional.pawn_storm,  &mut visit);
+        visit_score("king_semi_open_file".to_string(), &mut positional.king_semi_open_file, &mut visit);
+        visit_score("king_open_file".to_string(),      &mut positional.king_open_file,      &mut visit);
+    }
+
+    pub fn get_param(&self, name: &str) -> Option<isize> {
+        let mut result = None;
+        let mut copy = *self;
+        copy.visit_params(|param, value| if param == name { result = Some(*value) });
+        result
+    }
+
+    pub fn set_param(&mut self, name: &str, new_value: isize) -> bool {
+        let mut found = false;
+        self.visit_params(|param, value| if param == name {
+            *value = new_value;
+            found = true;
+        });
+        found
+    }
+
+    pub fn get_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut copy = *self;
+        copy.visit_params(|param, _| names.push(param));
+        names
+    }
+
+    pub fn get_values(&self) -> Vec<isize> {
+        let mut values = Vec::new();
+        let mut copy = *self;
+        copy.visit_params(|_, value| values.push(*value));
+        values
+    }
+
+    // values must be in the same order get_values returns them
+    pub fn set_values(&mut self, values: &[isize]) {
+        let mut values = values.iter();
+        self.visit_params(|_, value| *value = *values.next().unwrap());
+    }
+
+    // lines are "name = value", empty lines and lines starting with # are skipped,
+    // names missing from the text keep their current value
+    pub fn parse(&mut self, text: &str) -> Result<(), String> {
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let (name, value) = line.split_once('=').ok_or(format!("line {}: expected name = value", line_number + 1))?;
+            let value = value.trim().parse().map_err(|_| format!("line {}: invalid value {}", line_number + 1, value.trim()))?;
+            if !self.set_param(name.trim(), value) { return Err(format!("line {}: unknown parameter {}", line_number + 1, name.trim())); }
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("can not read {}: {}", path, err))?;
+        let mut params = Self::new();
+        params.parse(&text)?;
+        Ok(params)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_string()).map_err(|err| format!("can not write {}: {}", path, err))
+    }
+}
+
+impl Default for EvalParams {
+    fn default() -> Self { Self::new() }
+}
+
+impl std::fmt::Display for EvalParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut copy = *self;
+        let mut result = Ok(());
+        copy.visit_params(|name, value| {
+            if result.is_ok() { result = writeln!(f, "{} = {}", name, value); }
+        });
+        result
+    }
+}
+
+fn visit_square_table(name: &str, table: &mut ChessBoard<isize>, visit: &mut impl FnMut(String, &mut isize)) {
+    for square in Square::create_squares(0, 64) {
+        visit(format!("{}[{}]", name, square.get_uci_name()), &mut table[square]);
+    }
+}
+
+fn visit_score(name: String, score: &mut TaperedScore, visit: &mut impl FnMut(String, &mut isize)) {
+    visit(format!("{}.mg", name), &mut score.mg);
+    visit(format!("{}.eg", name), &mut score.eg);
+}
+
+fn visit_scores(name: &str, scores: &mut [TaperedScore], visit: &mut impl FnMut(String, &mut isize)) {
+    for (index, score) in scores.iter_mut().enumerate() {
+        visit_score(format!("{}[{}]", name, index), score, visit);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::board_components::Color;
+use crate::constants::board_constants::{LIGHT_SQUARES, START_POS};
+use crate::constants::squares::NO_SQUARE;
+use crate::debug::FenString;
+use crate::pieces::pieces_controller::{BoardSlots, BoardStatus, MoveBitField, UndoInfo};
+use crate::pieces::zobrist::ZOBRIST_KEYS;
+use crate::san::legal_moves;
+
+// FIDE 9.3 and 9.2 let a player claim the draw, 9.6 ends the game without a claim after
+// 75 moves or the fifth repetition
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DrawClaim {
+    Claimable,
+    Automatic,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameStatus {
+    Ongoing,
+    // the winner
+    Checkmate(Color),
+    Stalemate,
+    FiftyMove(DrawClaim),
+    Threefold(DrawClaim),
+    // neither side can mate: KvK, KBvK, KNvK or only bishops on squares of one colour
+    InsufficientMaterial,
+}
+
+impl GameStatus {
+    // over without anybody claiming it
+    pub fn is_game_over(&self) -> bool {
+        match self {
+            GameStatus::Ongoing => false,
+            GameStatus::FiftyMove(claim) | GameStatus::Threefold(claim) => *claim == DrawClaim::Automatic,
+            _ => true,
+        }
+    }
+
+    pub fn is_claimable(&self) -> bool {
+        matches!(self, GameStatus::FiftyMove(DrawClaim::Claimable) | GameStatus::Threefold(DrawClaim::Claimable))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw      => "1/2-1/2",
+        }
+    }
+}
+
+pub struct Game {
+    board:   BoardStatus,
+    history: Vec<(MoveBitField, UndoInfo)>,
+    // repetition_key of every position of the game, the current one is the last
+    hashes:  Vec<u64>,
+}
+
+impl Game {
+    pub fn new() -> Self { Self::from_board(FenString::new(START_POS.to_string()).convert_to_board()) }
+
+    pub fn from_board(board: BoardStatus) -> Self {
+        Self { board, history: Vec::new(), hashes: vec![repetition_key(&board)] }
+    }
+
+    pub fn from_fen(fen: &str) -> Self { Self::from_board(FenString::new(fen.to_string()).convert_to_board()) }
+
+    pub fn get_board(&self) -> &BoardStatus { &self.board }
+
+    pub fn get_moves(&self) -> impl Iterator<Item=MoveBitField> + '_ { self.history.iter().map(|(mov, _)| *mov) }
+
+    pub fn legal_moves(&self) -> Vec<MoveBitField> { legal_moves(&self.board) }
+
+    pub fn push(&mut self, mov: MoveBitField) -> Result<(), String> {
+        if !self.legal_moves().contains(&mov) { return Err(format!("illegal move {}", mov.get_move_name())); }
+        let undo = self.board.make_move(mov).unwrap();
+        self.history.push((mov, undo));
+        self.hashes.push(repetition_key(&self.board));
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<MoveBitField> {
+        let (mov, undo) = self.history.pop()?;
+        self.board.unmake_move(mov, &undo);
+        self.hashes.pop();
+        Some(mov)
+    }
+
+    // times the current position has appeared, only positions after the last capture or pawn move can repeat
+    pub fn get_repetitions(&self) -> usize {
+        let hash = repetition_key(&self.board);
+        self.hashes.iter().rev().take(self.board.get_half_move() + 1).filter(|other| **other == hash).count()
+    }
+
+    pub fn is_insufficient_material(&self) -> bool { is_insufficient_material(&self.board) }
+
+    // mate and stalemate come first, a mating move stands even if it is the 75th move
+    pub fn status(&self) -> GameStatus {
+        if self.legal_moves().is_empty() {
+            if !self.board.is_in_check() { return GameStatus::Stalemate; }
+            return GameStatus::Checkmate(if self.board.get_color() == Color::White { Color::Black } else { Color::White });
+        }
+        if self.is_insufficient_material() { return GameStatus::InsufficientMaterial; }
+        let (half_move, repetitions) = (self.board.get_half_move(), self.get_repetitions());
+        if half_move >= 150 { return GameStatus::FiftyMove(DrawClaim::Automatic); }
+        if repetitions >= 5 { return GameStatus::Threefold(DrawClaim::Automatic); }
+        if half_move >= 100 { return GameStatus::FiftyMove(DrawClaim::Claimable); }
+        if repetitions >= 3 { return GameStatus::Threefold(DrawClaim::Claimable); }
+        GameStatus::Ongoing
+    }
+
+    // None while the game goes on, claimable draws only count when claim_draw is set
+    pub fn outcome(&self, claim_draw: bool) -> Option<GameResult> {
+        match self.status() {
+            GameStatus::Ongoing => None,
+            GameStatus::Checkmate(Color::White) => Some(GameResult::WhiteWins),
+            GameStatus::Checkmate(Color::Black) => Some(GameResult::BlackWins),
+            status if status.is_game_over() || claim_draw => Some(GameResult::Draw),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self { Self::new() }
+}
+
+// the Zobrist hash without the en passant file when no en passant capture is legal,
+// FIDE counts those positions as the same as the one without the double push
+pub fn repetition_key(board: &BoardStatus) -> u64 {
+    let enpassant = board.get_enpassant();
+    if enpassant == NO_SQUARE || legal_moves(board).iter().any(|mov| mov.is_move_enpassant()) { return board.get_hash(); }
+    board.get_hash() ^ ZOBRIST_KEYS.enpassant[enpassant.get_file() as usize]
+}
+
+// no sequence of legal moves can mate: a lone minor piece, or bishops that all stand on one square color
+pub fn is_insufficient_material(board: &BoardStatus) -> bool {
+    let heavy = board[BoardSlots::WhitePawn] | board[BoardSlots::BlackPawn] | board[BoardSlots::WhiteRook] |
+        board[BoardSlots::BlackRook] | board[BoardSlots::WhiteQueen] | board[BoardSlots::BlackQueen];
+    if heavy.0 != 0 { return false; }
+    let knights = board[BoardSlots::WhiteKnight] | board[BoardSlots::BlackKnight];
+    let bishops = board[BoardSlots::WhiteBishop] | board[BoardSlots::BlackBishop];
+    if (knights | bishops).count_ones() <= 1 { return true; }
+    knights.0 == 0 && ((bishops & LIGHT_SQUARES).0 == 0 || (bishops & !LIGHT_SQUARES).0 == 0)
+}
@@ -0,0 +1,179 @@
+use crate::board_components::{Color, MagicNumGenerator};
+use crate::debug::FenString;
+use crate::constants::board_constants::START_POS;
+use crate::eveluation::{find_best_move, MATE_SCORE};
+use crate::eveluation_params::EvalParams;
+use crate::game::{is_insufficient_material, repetition_key};
+use crate::san::legal_moves;
+use crate::nnue::NnueState;
+use crate::pieces::pieces_controller::{BoardHistory, BoardStatus, MoveBitField};
+use crate::uci::UciInformation;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::str::SplitWhitespace;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+// Self-play data for network training. Every recorded position is written as one line
+//   <fen> | <score> | <result>
+// score is the search score in centipawns and result is 1.0, 0.5 or 0.0, both from white's point of view.
+
+const NODES_DEPTH: isize = 64;
+
+pub struct GensfenOptions {
+    pub depth:        isize,
+    pub nodes:        Option<usize>,
+    pub games:        usize,
+    pub random_plies: usize,
+    pub max_plies:    usize,
+    pub threads:      usize,
+    pub seed:         u32,
+    pub book:         Option<String>,
+    pub output:       String,
+}
+
+impl GensfenOptions {
+    pub fn new() -> Self {
+        Self {
+            depth:        6,
+            nodes:        None,
+            games:        100,
+            random_plies: 8,
+            max_plies:    400,
+            threads:      1,
+            seed:         1,
+            book:         None,
+            output:       "gensfen.txt".to_string(),
+        }
+    }
+
+    // gensfen [depth N] [nodes N] [games N] [random_plies N] [max_plies N] [threads N] [seed N] [book FILE] [output FILE]
+    pub fn parse(mut data: SplitWhitespace) -> Result<Self, String> {
+        let mut options = Self::new();
+        let mut depth_given = false;
+        while let Some(key) = data.next() {
+            let value = data.next().ok_or(format!("{} needs a value", key))?;
+            let number = || value.parse::<usize>().map_err(|_| format!("invalid value {} for {}", value, key));
+            match key {
+                "depth"        => { options.depth = number()? as isize; depth_given = true; },
+                "nodes"        => options.nodes        = Some(number()?),
+                "games"        => options.games        = number()?,
+                "random_plies" => options.random_plies = number()?,
+                "max_plies"    => options.max_plies    = number()?,
+                "threads"      => options.threads      = usize::max(number()?, 1),
+                "seed"         => options.seed         = number()? as u32,
+                "book"         => options.book         = Some(value.to_string()),
+                "output"       => options.output       = value.to_string(),
+                _              => return Err(format!("unknown gensfen option {}", key)),
+            }
+        }
+        // a node limit alone searches as deep as the nodes allow
+        if options.nodes.is_some() && !depth_given { options.depth = NODES_DEPTH; }
+        Ok(options)
+    }
+}
+
+impl Default for GensfenOptions {
+    fn default() -> Self { Self::new() }
+}
+
+// book files hold one position per line, EPD lines without move counters are accepted
+pub fn load_book(path: &str) -> Result<Vec<BoardStatus>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("can not read {}: {}", path, err))?;
+    let boards: Vec<BoardStatus> = text.lines().filter_map(|line| {
+        let fields: Vec<&str> = line.split(';').next()?.split_whitespace().take(4).collect();
+        if fields.len() < 4 { return None; }
+        Some(FenString::new(format!("{} 0 1", fields.join(" "))).convert_to_board())
+    }).collect();
+    if boards.is_empty() { return Err(format!("{} has no positions", path)); }
+    Ok(boards)
+}
+
+fn search(uci_info: &mut UciInformation, board: BoardStatus, options: &GensfenOptions) -> (MoveBitField, isize) {
+    uci_info.board = board;
+    uci_info.depth_limit = options.depth;
+    uci_info.nodes_limit = options.nodes;
+    uci_info.node_count = 0;
+    uci_info.start_time = Instant::now();
+    uci_info.board_history = BoardHistory::new();
+    find_best_move(uci_info);
+    (uci_info.board_history.found_best_move, uci_info.board_history.found_best_score)
+}
+
+// plays one game and returns its lines, games that end during the random opening are dropped
+pub fn play_game(uci_info: &mut UciInformation, options: &GensfenOptions, book: &[BoardStatus], rng: &mut MagicNumGenerator) -> Vec<String> {
+    let mut board = book[rng.get_random_u32() as usize % book.len()];
+    for _ in 0..options.random_plies {
+        let moves = legal_moves(&board);
+        if moves.is_empty() { return Vec::new(); }
+        board.make_move(moves[rng.get_random_u32() as usize % moves.len()]);
+    }
+
+    let mut positions: Vec<(String, isize)> = Vec::new();
+    let mut history = vec![repetition_key(&board)];
+    let white_result = loop {
+        let moves = legal_moves(&board);
+        if moves.is_empty() {
+            if !board.is_in_check() { break 0.5; }
+            break if board.get_color() == Color::White { 0.0 } else { 1.0 };
+        }
+        let repetitions = history.iter().filter(|key| **key == history[history.len() - 1]).count();
+        if board.get_half_move() >= 100 || repetitions >= 3 || is_insufficient_material(&board) || history.len() > options.max_plies {
+            break 0.5;
+        }
+
+        let (mut mov, score) = search(uci_info, board, options);
+        let white_score = if board.get_color() == Color::White { score } else { -score };
+        if mov == MoveBitField::NO_MOVE { mov = moves[0]; }
+        else if score.abs() >= MATE_SCORE { break if white_score > 0 { 1.0 } else { 0.0 }; }
+        else if !board.is_in_check() && !mov.is_move_capture() {
+            positions.push((FenString::from_board(&board).get_fen_string(), white_score));
+        }
+
+        board.make_move(mov);
+        history.push(repetition_key(&board));
+    };
+
+    positions.into_iter().map(|(fen, score)| format!("{} | {} | {:.1}", fen, score, white_result)).collect()
+}
+
+// games are handed out by index and every game has its own generator, so the same seed
+// gives the same games whatever the thread count is, only the order in the file changes
+pub fn gensfen(options: &GensfenOptions, eval_params: EvalParams, nnue: Option<NnueState>) -> Result<usize, String> {
+    let book = match &options.book {
+        Some(path) => load_book(path)?,
+        None       => vec![FenString::new(START_POS.to_string()).convert_to_board()],
+    };
+    let file = File::create(&options.output).map_err(|err| format!("can not create {}: {}", options.output, err))?;
+    let writer = Mutex::new(BufWriter::new(file));
+    let next_game = AtomicUsize::new(0);
+    let positions = AtomicUsize::new(0);
+    let start_time = Instant::now();
+
+    std::thread::scope(|scope| {
+        for _ in 0..options.threads {
+            let nnue = nnue.clone();
+            let (book, writer, next_game, positions) = (&book, &writer, &next_game, &positions);
+            scope.spawn(move || {
+                let mut uci_info = UciInformation::new().set_eval_params(eval_params).set_nnue(nnue);
+                loop {
+                    let game = next_game.fetch_add(1, Ordering::Relaxed);
+                    if game >= options.games { break; }
+                    let mut rng = MagicNumGenerator::from_seed(options.seed ^ (game as u32 + 1).wrapping_mul(0x9E37_79B9));
+                    let lines = play_game(&mut uci_info, options, book, &mut rng);
+
+                    let mut writer = writer.lock().unwrap();
+                    for line in &lines { let _ = writeln!(writer, "{}", line); }
+                    let total = positions.fetch_add(lines.len(), Ordering::Relaxed) + lines.len();
+                    if (game + 1) % 100 == 0 {
+                        println!("info string gensfen {} games {} positions {:.0}s", game + 1, total, start_time.elapsed().as_secs_f64());
+                    }
+                }
+            });
+        }
+    });
+
+    writer.into_inner().unwrap().flush().map_err(|err| format!("can not write {}: {}", options.output, err))?;
+    Ok(positions.into_inner())
+}
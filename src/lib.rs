@@ -4,43 +4,39 @@ pub mod pieces;
 pub mod helper_macros;
 pub mod debug;
 pub mod eveluation;
+pub mod eveluation_params;
+pub mod nnue;
+pub mod gensfen;
+pub mod bench;
+pub mod san;
+pub mod epd;
+pub mod game;
+pub mod position;
+pub mod perft;
 pub mod uci;
+pub mod tuner;
+pub mod magics;
+pub mod render;
+pub mod xboard;
 
-use debug::FenString;
-use pieces::tables::init_statics;
-use crate::pieces::pieces_controller::{MoveList, is_square_attacked_black, is_square_attacked_white, BoardSlots};
-use board_components::Color;
-use uci::UciInformation;
+pub use board_components::{Color, File, Rank, Square};
+pub use position::{Error, LegalMoves, Move, Piece, PieceKind, Position};
 
-pub fn init_all_statics() { init_statics(); }
+// the tables are built at compile time, this is only kept for older callers
+pub fn init_all_statics() {}
 
+// String versions of the Position API, they panic on an invalid fen or move
 pub fn get_moves(fen: String) -> Vec<String> {
-    let board = FenString::new(fen.clone()).convert_to_board();
-    let moves = MoveList::new(&UciInformation::new().set_board(board));
-    moves.iterate_moves().filter(|mov| {
-        let mut dummy = FenString::new(fen.clone()).convert_to_board();
-        dummy.make_move(*mov)
-    }).map(|mov| mov.get_move_name()).collect()
+    let position: Position = fen.parse().unwrap();
+    position.legal_moves().map(|mov| mov.to_string()).collect()
 }
 
 pub fn make_move(fen: String, move_name: String) -> String {
-    let mut board = FenString::new(fen).convert_to_board();
-    let moves = MoveList::new(&UciInformation::new().set_board(board));
-    let mov = moves.iterate_moves().find(|mov| mov.get_move_name() == move_name).unwrap();
-    board.make_move(mov);
-    FenString::from_board(&board).get_fen_string()
+    let position: Position = fen.parse().unwrap();
+    let mov = position.parse_move(&move_name).unwrap();
+    position.play(mov).unwrap().get_fen()
 }
 
 pub fn is_king_attacked(fen: String) -> bool {
-    let board = FenString::new(fen).convert_to_board();
-    match board.get_color() {
-        Color::White => {
-            let square = board[BoardSlots::WhiteKing].get_lsb_index();
-            is_square_attacked_white(&board, square)
-        }
-        Color::Black => {
-            let square = board[BoardSlots::BlackKing].get_lsb_index();
-            is_square_attacked_black(&board, square)
-        }
-    }
-}
\ No newline at end of file
+    fen.parse::<Position>().unwrap().is_in_check()
+}
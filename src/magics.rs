@@ -0,0 +1,144 @@
+use crate::board_components::{BitBoard, ChessBoard, MagicNum, MagicNumGenerator, Square};
+use crate::constants::board_constants::{create_bishop_magics, create_bishop_move_counts, create_rook_magics, create_rook_move_counts};
+use crate::pieces::tables::{bishop_attacks_on_fly, get_table_size, mask_bishop_attacks, mask_rook_attacks, rook_attacks_on_fly,
+    search_magic_number, verify_magic_number};
+
+// The magics and move counts in constants are the output of this module. A move count is the number of
+// index bits of its square, the tables give every square 2^move count entries, so fewer bits mean a smaller table.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Slider {
+    Rook,
+    Bishop,
+}
+
+impl Slider {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Slider::Rook   => "rook",
+            Slider::Bishop => "bishop",
+        }
+    }
+
+    pub fn get_mask(&self) -> fn(Square) -> BitBoard {
+        match self {
+            Slider::Rook   => mask_rook_attacks,
+            Slider::Bishop => mask_bishop_attacks,
+        }
+    }
+
+    pub fn get_attacks_on_fly(&self) -> fn(Square, BitBoard) -> BitBoard {
+        match self {
+            Slider::Rook   => rook_attacks_on_fly,
+            Slider::Bishop => bishop_attacks_on_fly,
+        }
+    }
+}
+
+pub struct MagicOptions {
+    pub seed:  u32,
+    pub tries: usize,
+    // index bits of every square, None uses the bits of the square's mask
+    pub bits:  Option<u32>,
+    // keeps taking a bit away from each square while a magic is still found
+    pub fancy: bool,
+    // called with the bits and magic of every square once it is found
+    pub report: Option<fn(Slider, Square, u32, MagicNum)>,
+}
+
+impl MagicOptions {
+    pub fn new() -> Self { Self { seed: 0, tries: 100000000, bits: None, fancy: false, report: None } }
+
+    pub fn set_seed(mut self, seed: u32) -> Self {
+        self.seed = seed;
+        self
+    }
+    pub fn set_tries(mut self, tries: usize) -> Self {
+        self.tries = tries;
+        self
+    }
+    pub fn set_bits(mut self, bits: Option<u32>) -> Self {
+        self.bits = bits;
+        self
+    }
+    pub fn set_fancy(mut self, fancy: bool) -> Self {
+        self.fancy = fancy;
+        self
+    }
+    pub fn set_report(mut self, report: fn(Slider, Square, u32, MagicNum)) -> Self {
+        self.report = Some(report);
+        self
+    }
+}
+
+impl Default for MagicOptions {
+    fn default() -> Self { Self::new() }
+}
+
+pub struct MagicSet {
+    pub slider:      Slider,
+    pub magics:      ChessBoard<MagicNum>,
+    pub move_counts: ChessBoard<u64>,
+}
+
+impl MagicSet {
+    // the set the engine is built with
+    pub fn current(slider: Slider) -> Self {
+        match slider {
+            Slider::Rook   => Self { slider, magics: create_rook_magics(), move_counts: create_rook_move_counts() },
+            Slider::Bishop => Self { slider, magics: create_bishop_magics(), move_counts: create_bishop_move_counts() },
+        }
+    }
+
+    pub fn get_table_size(&self) -> usize { get_table_size(&self.move_counts) }
+
+    pub fn verify(&self) -> Result<(), String> {
+        let failed: Vec<String> = (0..64).map(Square).filter(|square| {
+            !verify_magic_number(self.slider.get_mask(), self.slider.get_attacks_on_fly(), *square, self.magics[*square], self.move_counts[*square] as u32)
+        }).map(|square| square.to_string()).collect();
+        if failed.is_empty() { Ok(()) } else { Err(format!("{} magics fail on {}", self.slider.get_name(), failed.join(" "))) }
+    }
+
+    // written like constants::board_constants, ready to replace the functions there
+    pub fn get_rust_source(&self) -> String {
+        let name = self.slider.get_name();
+        let move_counts: Vec<String> = self.move_counts.0.chunks(8)
+            .map(|row| row.iter().map(u64::to_string).collect::<Vec<String>>().join(", ")).collect();
+        let magics: Vec<String> = self.magics.0.chunks(4)
+            .map(|row| row.iter().map(|magic| format!("{:<30}", format!("MagicNum(0x{:X}),", magic.0))).collect::<String>().trim_end().to_string()).collect();
+        let mut source = format!("    pub const fn create_{}_move_counts() -> ChessBoard<u64> {{\n        ChessBoard([\n", name);
+        source += &move_counts.iter().map(|row| format!("            {}", row)).collect::<Vec<String>>().join(",\n");
+        source += "\n            ])\n        }\n";
+        source += &format!("    // these numbers generated by the magics binary\n    pub const fn create_{}_magics() -> ChessBoard<MagicNum> {{\n        ChessBoard([\n", name);
+        source += &magics.iter().map(|row| format!("            {}\n", row)).collect::<String>();
+        source += "        ])\n    }\n";
+        source
+    }
+}
+
+// every square is searched with one generator, so a seed always gives the same set
+pub fn search_magics(slider: Slider, options: &MagicOptions) -> Result<MagicSet, String> {
+    if options.bits.is_some_and(|bits| bits == 0 || bits > 12) { return Err("bits must be between 1 and 12".to_string()); }
+    let mut generator = MagicNumGenerator::from_seed(options.seed);
+    let (mask, on_fly) = (slider.get_mask(), slider.get_attacks_on_fly());
+    let mut magics = [MagicNum(0); 64];
+    let mut move_counts = [0; 64];
+    for square in (0..64).map(Square) {
+        let mut bits = options.bits.unwrap_or(mask(square).count_ones());
+        let Some(mut magic) = search_magic_number(mask, on_fly, square, bits, &mut generator, options.tries) else {
+            return Err(format!("no {} magic with {} bits for {} in {} tries", slider.get_name(), bits, square, options.tries));
+        };
+        while options.fancy && bits > 1 {
+            match search_magic_number(mask, on_fly, square, bits - 1, &mut generator, options.tries) {
+                Some(smaller) => (magic, bits) = (smaller, bits - 1),
+                None => break,
+            }
+        }
+        if let Some(report) = options.report { report(slider, square, bits, magic); }
+        magics[square.0 as usize] = magic;
+        move_counts[square.0 as usize] = bits as u64;
+    }
+    let set = MagicSet { slider, magics: ChessBoard::from(magics), move_counts: ChessBoard::from(move_counts) };
+    set.verify()?;
+    Ok(set)
+}
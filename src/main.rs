@@ -1,15 +1,44 @@
-pub mod pieces;
-pub mod board_components;
-pub mod constants;
-pub mod debug;
-pub mod eveluation;
-pub mod helper_macros;
-pub mod uci;
+use persa_chess::debug::perft_diff_terminal;
+use persa_chess::pieces::tables::check_cpu_features;
+use persa_chess::uci::{generate_data, run_bench, run_epd_suite, run_perft_suite, run_render, uci_loop, UciInformation};
+use persa_chess::xboard::xboard_loop;
+use std::env;
+use std::process::exit;
 
-use pieces::tables::init_statics;
-use uci::uci_loop;
+const USAGE: &str = "usage: persa [command]
+  uci                                     UCI mode, the default without a command, xboard as the first command switches to CECP
+  xboard                                  CECP (xboard) mode
+  perft <depth> <fen> [moves]             perftree compatible divide
+  perftsuite <depth> [threads N] [hash MB] [file PATH]
+  bench [depth] [threads] [hash]          fixed depth search of the bench positions
+  bench sliders [iterations]              slider attack lookups of the magic and pext backends
+  epd <file> [file ...] [depth N] [nodes N] [movetime MS] [output FILE]
+  render <ascii|unicode|ansi|svg> [flip] [nocoords] [lastmove MOVE] [arrow MOVE] [output FILE] <startpos | fen>
+  gensfen [depth N] [nodes N] [games N] [random_plies N] [threads N] [seed N] [book FILE] [output FILE]";
 
 fn main() {
-    init_statics();
-    uci_loop();
-}   
\ No newline at end of file
+    if let Err(err) = check_cpu_features() {
+        eprintln!("{}", err);
+        exit(1);
+    }
+    let args: Vec<String> = env::args().skip(1).collect();
+    let rest = args.iter().skip(1).map(String::as_str).collect::<Vec<&str>>().join(" ");
+    match args.first().map(String::as_str) {
+        None | Some("uci") => uci_loop(),
+        Some("xboard")     => xboard_loop(UciInformation::new()),
+        Some("perft")      => if let Err(err) = perft_diff_terminal(&args[1..]) {
+            eprintln!("{}", err);
+            exit(1);
+        },
+        Some("perftsuite") => run_perft_suite(rest.split_whitespace()),
+        Some("bench")      => run_bench(rest.split_whitespace(), &UciInformation::new()),
+        Some("epd")        => run_epd_suite(rest.split_whitespace(), &UciInformation::new()),
+        Some("render")     => run_render(rest.split_whitespace()),
+        Some("gensfen")    => generate_data(rest.split_whitespace(), &UciInformation::new()),
+        Some("help")       => println!("{}", USAGE),
+        Some(command)      => {
+            eprintln!("unknown command {}\n{}", command, USAGE);
+            exit(1);
+        },
+    }
+}
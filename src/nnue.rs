@@ -0,0 +1,259 @@
+use crate::board_components::{Color, Square};
+use crate::pieces::pieces_controller::{BoardSlots, BoardStatus};
+use std::mem::transmute;
+use std::sync::Arc;
+
+// (768 -> HIDDEN_SIZE) x 2 -> 1 network. Every (piece, square) pair is an input, both sides
+// keep their own accumulator from their own point of view, and the output layer reads the
+// side to move accumulator first.
+//
+// File layout, all numbers little endian:
+//   4 bytes  magic "PRSN"
+//   u32      hidden size, has to be HIDDEN_SIZE
+//   i16      feature weights [INPUT_SIZE][HIDDEN_SIZE]
+//   i16      feature bias    [HIDDEN_SIZE]
+//   i16      output weights  [2][HIDDEN_SIZE], side to move half first
+//   i32      output bias
+pub const INPUT_SIZE:  usize = 768;
+pub const HIDDEN_SIZE: usize = 256;
+const QA:    i32 = 255;
+const QB:    i32 = 64;
+const SCALE: i32 = 400;
+const MAGIC: &[u8; 4] = b"PRSN";
+const FILE_SIZE: usize = 8 + (INPUT_SIZE * HIDDEN_SIZE + HIDDEN_SIZE + 2 * HIDDEN_SIZE) * 2 + 4;
+
+// board changes of the last make_move, filled by BoardStatus::set_piece_bit and remove_piece.
+// A move touches at most 4 pieces (castling), anything more means the board was set up
+// from scratch and the accumulator has to be refreshed.
+#[derive(Clone, Copy)]
+pub struct DirtyPieces {
+    changes: [(u8, u8, bool); 4],
+    count: u8,
+}
+
+impl DirtyPieces {
+    pub const fn new() -> Self { Self { changes: [(0, 0, false); 4], count: 0 } }
+
+    #[inline(always)]
+    pub fn clear(&mut self) { self.count = 0; }
+
+    #[inline(always)]
+    pub fn push(&mut self, piece: BoardSlots, square: Square, added: bool) {
+        if (self.count as usize) < self.changes.len() {
+            self.changes[self.count as usize] = (piece as u8, square.0, added);
+        }
+        self.count = self.count.saturating_add(1);
+    }
+
+    #[inline(always)]
+    pub fn is_overflowed(&self) -> bool { self.count as usize > self.changes.len() }
+
+    #[inline(always)]
+    pub fn iterate_changes(&self) -> impl Iterator<Item = (BoardSlots, Square, bool)> + '_ {
+        self.changes[..usize::min(self.count as usize, self.changes.len())].iter()
+            .map(|&(piece, square, added)| (unsafe { transmute::<u8, BoardSlots>(piece) }, Square(square), added))
+    }
+}
+
+impl Default for DirtyPieces {
+    fn default() -> Self { Self::new() }
+}
+
+#[derive(Clone, Copy)]
+pub struct Accumulator {
+    pub values: [[i16; HIDDEN_SIZE]; 2],
+}
+
+pub struct Network {
+    feature_weights: Box<[[i16; HIDDEN_SIZE]; INPUT_SIZE]>,
+    feature_bias:    [i16; HIDDEN_SIZE],
+    output_weights:  [[i16; HIDDEN_SIZE]; 2],
+    output_bias:     i32,
+    use_simd:        bool,
+}
+
+#[inline(always)]
+fn feature_index(perspective: Color, piece: BoardSlots, square: Square) -> usize {
+    match perspective {
+        Color::White => piece as usize * 64 + square.0 as usize,
+        Color::Black => ((piece as usize + 6) % 12) * 64 + (square.0 ^ 56) as usize,
+    }
+}
+
+impl Network {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != FILE_SIZE { return Err(format!("network has {} bytes, expected {}", bytes.len(), FILE_SIZE)); }
+        if &bytes[0..4] != MAGIC { return Err("network file does not start with PRSN".to_string()); }
+        let hidden_size = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+        if hidden_size != HIDDEN_SIZE { return Err(format!("network hidden size is {}, expected {}", hidden_size, HIDDEN_SIZE)); }
+
+        let mut numbers = bytes[8..].chunks_exact(2).map(|pair| i16::from_le_bytes([pair[0], pair[1]]));
+        let mut feature_weights: Box<[[i16; HIDDEN_SIZE]; INPUT_SIZE]> = vec![[0; HIDDEN_SIZE]; INPUT_SIZE].into_boxed_slice().try_into().unwrap();
+        for row in feature_weights.iter_mut() {
+            row.iter_mut().for_each(|weight| *weight = numbers.next().unwrap());
+        }
+        let mut feature_bias = [0; HIDDEN_SIZE];
+        feature_bias.iter_mut().for_each(|bias| *bias = numbers.next().unwrap());
+        let mut output_weights = [[0; HIDDEN_SIZE]; 2];
+        output_weights.iter_mut().flatten().for_each(|weight| *weight = numbers.next().unwrap());
+        let output_bias = i32::from_le_bytes(bytes[FILE_SIZE - 4..].try_into().unwrap());
+
+        Ok(Self { feature_weights, feature_bias, output_weights, output_bias, use_simd: simd_available() })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|err| format!("can not read {}: {}", path, err))?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn set_simd(&mut self, enabled: bool) { self.use_simd = enabled && simd_available(); }
+
+    pub fn refresh(&self, board_status: &BoardStatus, accumulator: &mut Accumulator) {
+        for perspective in [Color::White, Color::Black] {
+            let values = &mut accumulator.values[perspective as usize];
+            *values = self.feature_bias;
+            for piece in BoardSlots::iterate_pieces() {
+                for square in board_status[piece] {
+                    self.add_row(values, feature_index(perspective, piece, square));
+                }
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn update(&self, board_status: &BoardStatus, accumulator: &mut Accumulator) {
+        let dirty = board_status.get_dirty_pieces();
+        if dirty.is_overflowed() { return self.refresh(board_status, accumulator); }
+        for (piece, square, added) in dirty.iterate_changes() {
+            for perspective in [Color::White, Color::Black] {
+                let values = &mut accumulator.values[perspective as usize];
+                if added { self.add_row(values, feature_index(perspective, piece, square)); }
+                else { self.sub_row(values, feature_index(perspective, piece, square)); }
+            }
+        }
+    }
+
+    // score in centipawns from the side to move point of view, with full range weights
+    // a single dot product already gets close to i32::MAX so the sums are i64
+    #[inline(always)]
+    pub fn eveluate(&self, accumulator: &Accumulator, color: Color) -> isize {
+        let us   = &accumulator.values[color as usize];
+        let them = &accumulator.values[color as usize ^ 1];
+        let sum = self.crelu_dot(us, &self.output_weights[0]) + self.crelu_dot(them, &self.output_weights[1]);
+        ((sum + self.output_bias as i64) * SCALE as i64 / (QA * QB) as i64) as isize
+    }
+
+    #[inline(always)]
+    fn add_row(&self, values: &mut [i16; HIDDEN_SIZE], index: usize) {
+        let row = &self.feature_weights[index];
+        #[cfg(target_arch = "x86_64")]
+        if self.use_simd { return unsafe { simd::add_row(values, row) }; }
+        values.iter_mut().zip(row).for_each(|(value, weight)| *value = value.wrapping_add(*weight));
+    }
+
+    #[inline(always)]
+    fn sub_row(&self, values: &mut [i16; HIDDEN_SIZE], index: usize) {
+        let row = &self.feature_weights[index];
+        #[cfg(target_arch = "x86_64")]
+        if self.use_simd { return unsafe { simd::sub_row(values, row) }; }
+        values.iter_mut().zip(row).for_each(|(value, weight)| *value = value.wrapping_sub(*weight));
+    }
+
+    #[inline(always)]
+    fn crelu_dot(&self, values: &[i16; HIDDEN_SIZE], weights: &[i16; HIDDEN_SIZE]) -> i64 {
+        #[cfg(target_arch = "x86_64")]
+        if self.use_simd { return unsafe { simd::crelu_dot(values, weights) }; }
+        values.iter().zip(weights).map(|(&value, &weight)| ((value as i32).clamp(0, QA) * weight as i32) as i64).sum()
+    }
+}
+
+fn simd_available() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    { is_x86_feature_detected!("avx2") }
+    #[cfg(not(target_arch = "x86_64"))]
+    { false }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod simd {
+    use super::{HIDDEN_SIZE, QA};
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn add_row(values: &mut [i16; HIDDEN_SIZE], row: &[i16; HIDDEN_SIZE]) {
+        for i in (0..HIDDEN_SIZE).step_by(16) {
+            let value  = _mm256_loadu_si256(values.as_ptr().add(i) as *const __m256i);
+            let weight = _mm256_loadu_si256(row.as_ptr().add(i) as *const __m256i);
+            _mm256_storeu_si256(values.as_mut_ptr().add(i) as *mut __m256i, _mm256_add_epi16(value, weight));
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn sub_row(values: &mut [i16; HIDDEN_SIZE], row: &[i16; HIDDEN_SIZE]) {
+        for i in (0..HIDDEN_SIZE).step_by(16) {
+            let value  = _mm256_loadu_si256(values.as_ptr().add(i) as *const __m256i);
+            let weight = _mm256_loadu_si256(row.as_ptr().add(i) as *const __m256i);
+            _mm256_storeu_si256(values.as_mut_ptr().add(i) as *mut __m256i, _mm256_sub_epi16(value, weight));
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    // every i32 lane adds up HIDDEN_SIZE / 8 products, which fits, the lanes are added up in i64
+    pub unsafe fn crelu_dot(values: &[i16; HIDDEN_SIZE], weights: &[i16; HIDDEN_SIZE]) -> i64 {
+        let zero = _mm256_setzero_si256();
+        let max  = _mm256_set1_epi16(QA as i16);
+        let mut sum = _mm256_setzero_si256();
+        for i in (0..HIDDEN_SIZE).step_by(16) {
+            let value  = _mm256_loadu_si256(values.as_ptr().add(i) as *const __m256i);
+            let weight = _mm256_loadu_si256(weights.as_ptr().add(i) as *const __m256i);
+            let value  = _mm256_min_epi16(_mm256_max_epi16(value, zero), max);
+            sum = _mm256_add_epi32(sum, _mm256_madd_epi16(value, weight));
+        }
+        let low  = _mm256_cvtepi32_epi64(_mm256_castsi256_si128(sum));
+        let high = _mm256_cvtepi32_epi64(_mm256_extracti128_si256(sum, 1));
+        let sum  = _mm256_add_epi64(low, high);
+        let sum  = _mm_add_epi64(_mm256_castsi256_si128(sum), _mm256_extracti128_si256(sum, 1));
+        _mm_cvtsi128_si64(_mm_add_epi64(sum, _mm_unpackhi_epi64(sum, sum)))
+    }
+}
+
+// accumulator stack of the search, push after every make_move and pop after the child returns
+#[derive(Clone)]
+pub struct NnueState {
+    network: Arc<Network>,
+    stack:   Vec<Accumulator>,
+    index:   usize,
+}
+
+impl NnueState {
+    pub fn new(network: Arc<Network>) -> Self {
+        Self { network, stack: vec![Accumulator { values: [[0; HIDDEN_SIZE]; 2] }], index: 0 }
+    }
+
+    pub fn get_network(&self) -> &Network { &self.network }
+
+    pub fn reset(&mut self, board_status: &BoardStatus) {
+        self.index = 0;
+        self.network.refresh(board_status, &mut self.stack[0]);
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, board_status: &BoardStatus) {
+        if self.index + 1 == self.stack.len() { self.stack.push(self.stack[self.index]); }
+        let (parents, children) = self.stack.split_at_mut(self.index + 1);
+        children[0] = parents[self.index];
+        self.index += 1;
+        self.network.update(board_status, &mut children[0]);
+    }
+
+    #[inline(always)]
+    pub fn pop(&mut self) { self.index -= 1; }
+
+    #[inline(always)]
+    pub fn get_accumulator(&self) -> &Accumulator { &self.stack[self.index] }
+
+    #[inline(always)]
+    pub fn eveluate(&self, color: Color) -> isize {
+        self.network.eveluate(self.get_accumulator(), color)
+    }
+}
@@ -0,0 +1,217 @@
+use crate::board_components::{BitBoard, Color, Square};
+use crate::debug::FenString;
+use crate::pieces::pieces_controller::{BoardSlots, BoardStatus, MoveBitField, MoveList};
+use crate::pieces::tables::{generate_bishop_attacks, generate_king_attacks, generate_knight_attacks, generate_rook_attakcs, genereate_pawn_attacks};
+use std::fs;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+// Shared transposition table for perft. An entry keeps (hash ^ data, data) so threads can
+// write without locks, a torn write just fails the key check and counts as a miss.
+pub struct PerftTable {
+    entries: Vec<(AtomicU64, AtomicU64)>,
+}
+
+impl PerftTable {
+    pub fn new(size_mb: usize) -> Self {
+        let count = 1 << usize::max(size_mb * 1024 * 1024 / 16, 1).ilog2();
+        Self { entries: (0..count).map(|_| (AtomicU64::new(0), AtomicU64::new(0))).collect() }
+    }
+
+    #[inline(always)]
+    fn get_entry(&self, hash: u64) -> &(AtomicU64, AtomicU64) {
+        &self.entries[hash as usize & (self.entries.len() - 1)]
+    }
+
+    #[inline(always)]
+    pub fn probe(&self, hash: u64, depth: usize) -> Option<u64> {
+        let (key, data) = self.get_entry(hash);
+        let (key, data) = (key.load(Ordering::Relaxed), data.load(Ordering::Relaxed));
+        if key ^ data == hash && (data & 0xff) as usize == depth { Some(data >> 8) } else { None }
+    }
+
+    #[inline(always)]
+    pub fn store(&self, hash: u64, depth: usize, nodes: u64) {
+        let data = (nodes << 8) | depth as u64;
+        let (key, entry_data) = self.get_entry(hash);
+        key.store(hash ^ data, Ordering::Relaxed);
+        entry_data.store(data, Ordering::Relaxed);
+    }
+}
+
+// bulk counting: one ply above the leaves the legal moves are counted instead of searched
+pub fn perft(board: &mut BoardStatus, depth: usize, table: Option<&PerftTable>) -> u64 {
+    if depth == 0 { return 1; }
+    if let Some(nodes) = table.and_then(|table| table.probe(board.get_hash(), depth)) { return nodes; }
+    let mut nodes = 0;
+    for mov in MoveList::generate(board).iterate_moves() {
+        if let Some(undo) = board.make_move(mov) {
+            nodes += if depth == 1 { 1 } else { perft(board, depth - 1, table) };
+            board.unmake_move(mov, &undo);
+        }
+    }
+    if let Some(table) = table { table.store(board.get_hash(), depth, nodes); }
+    nodes
+}
+
+pub struct PerftResult {
+    // root moves sorted by name with their node counts
+    pub divide:  Vec<(String, u64)>,
+    pub nodes:   u64,
+    pub seconds: f64,
+}
+
+impl PerftResult {
+    pub fn get_nps(&self) -> u64 {
+        if self.seconds > 0.0 { (self.nodes as f64 / self.seconds) as u64 } else { 0 }
+    }
+}
+
+// root moves are handed out one by one, every thread works on its own copy of the board
+pub fn perft_divide(board: &BoardStatus, depth: usize, threads: usize, table: Option<&PerftTable>, chess960: bool) -> PerftResult {
+    let start_time = Instant::now();
+    let root_moves: Vec<MoveBitField> = MoveList::generate(board).iterate_moves().filter(|mov| {
+        let mut copy = *board;
+        copy.make_move(*mov).is_some()
+    }).collect();
+    let next_move = AtomicUsize::new(0);
+    let divide = Mutex::new(Vec::new());
+
+    if depth > 0 {
+        std::thread::scope(|scope| {
+            for _ in 0..usize::max(threads, 1) {
+                scope.spawn(|| {
+                    let mut board = *board;
+                    loop {
+                        let index = next_move.fetch_add(1, Ordering::Relaxed);
+                        let Some(&mov) = root_moves.get(index) else { break };
+                        let undo = board.make_move(mov).unwrap();
+                        let nodes = perft(&mut board, depth - 1, table);
+                        board.unmake_move(mov, &undo);
+                        divide.lock().unwrap().push((mov.get_uci_name(chess960), nodes));
+                    }
+                });
+            }
+        });
+    }
+
+    let mut divide = divide.into_inner().unwrap();
+    divide.sort();
+    let nodes = if depth == 0 { 1 } else { divide.iter().map(|(_, nodes)| nodes).sum() };
+    PerftResult { divide, nodes, seconds: start_time.elapsed().as_secs_f64() }
+}
+
+pub fn print_perft(result: &PerftResult) {
+    for (name, nodes) in &result.divide { println!("{}: {}", name, nodes); }
+    println!("\nNodes searched: {}", result.nodes);
+    println!("Time: {:.3}s NPS: {}", result.seconds, result.get_nps());
+}
+
+// runs every "<fen> ;D1 n ;D2 n ..." line of a perft file up to max_depth, returns the failed (fen, depth) pairs
+pub fn perft_suite(path: &str, max_depth: usize, threads: usize, hash_mb: usize) -> Result<Vec<(String, usize)>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("can not read {}: {}", path, err))?;
+    let table = (hash_mb > 0).then(|| PerftTable::new(hash_mb));
+    let mut failed = Vec::new();
+    let (mut total_nodes, mut total_seconds) = (0, 0.0);
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap().trim();
+        let board = FenString::new(fen.to_string()).convert_to_board();
+        for field in fields {
+            let Some((depth, expected)) = field.trim().trim_start_matches('D').split_once(' ') else { continue };
+            let (Ok(depth), Ok(expected)) = (depth.parse::<usize>(), expected.trim().parse::<u64>()) else { continue };
+            if depth > max_depth { continue; }
+            let result = perft_divide(&board, depth, threads, table.as_ref(), false);
+            total_nodes += result.nodes;
+            total_seconds += result.seconds;
+            let status = if result.nodes == expected { "ok" } else { "FAILED" };
+            println!("{} depth {} nodes {} expected {} {}", fen, depth, result.nodes, expected, status);
+            if result.nodes != expected { failed.push((fen.to_string(), depth)); }
+        }
+    }
+    let nps = if total_seconds > 0.0 { (total_nodes as f64 / total_seconds) as u64 } else { 0 };
+    println!("\n{} failed, nodes {} time {:.3}s NPS {}", failed.len(), total_nodes, total_seconds, nps);
+    Ok(failed)
+}
+
+// same columns as the perft results tables on the chessprogramming wiki, captures include en passant
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PerftStats {
+    pub nodes:             u64,
+    pub captures:          u64,
+    pub en_passant:        u64,
+    pub castles:           u64,
+    pub promotions:        u64,
+    pub checks:            u64,
+    pub discovered_checks: u64,
+    pub double_checks:     u64,
+    pub checkmates:        u64,
+}
+
+// pieces of `color` attacking the square
+fn attackers(board: &BoardStatus, square: Square, color: Color) -> BitBoard {
+    let (pawn, knight, bishop, rook, queen, king, pawn_color) = match color {
+        Color::White => (BoardSlots::WhitePawn, BoardSlots::WhiteKnight, BoardSlots::WhiteBishop, BoardSlots::WhiteRook, BoardSlots::WhiteQueen, BoardSlots::WhiteKing, Color::Black),
+        Color::Black => (BoardSlots::BlackPawn, BoardSlots::BlackKnight, BoardSlots::BlackBishop, BoardSlots::BlackRook, BoardSlots::BlackQueen, BoardSlots::BlackKing, Color::White),
+    };
+    let bishop_attacks = generate_bishop_attacks(square, board[BoardSlots::AllPieces]);
+    let rook_attacks   = generate_rook_attakcs(square, board[BoardSlots::AllPieces]);
+    genereate_pawn_attacks(square, pawn_color) & board[pawn] |
+    generate_knight_attacks(square) & board[knight] |
+    bishop_attacks & (board[bishop] | board[queen]) |
+    rook_attacks & (board[rook] | board[queen]) |
+    generate_king_attacks(square) & board[king]
+}
+
+fn has_legal_move(board: &mut BoardStatus) -> bool {
+    MoveList::generate(board).iterate_moves().any(|mov| match board.make_move(mov) {
+        Some(undo) => { board.unmake_move(mov, &undo); true },
+        None => false,
+    })
+}
+
+// stats[ply] counts the moves played at that ply, so one run fills every depth up to the requested one
+fn collect_stats(board: &mut BoardStatus, ply: usize, stats: &mut [PerftStats]) {
+    for mov in MoveList::generate(board).iterate_moves() {
+        let mover = board.get_color();
+        // the piece that moved, for castling it is the rook that can give check
+        let moved_square = if mov.is_move_castling() { board.get_castle_of_move(mov).get_targets().1 } else { mov.get_target() };
+        let Some(undo) = board.make_move(mov) else { continue };
+        let entry = &mut stats[ply];
+        entry.nodes += 1;
+        if mov.is_move_capture() { entry.captures += 1; }
+        if mov.is_move_enpassant() { entry.en_passant += 1; }
+        if mov.is_move_castling() { entry.castles += 1; }
+        if MoveBitField::is_move_promoted(mov.get_promoted()) { entry.promotions += 1; }
+
+        let king = if mover == Color::White { BoardSlots::BlackKing } else { BoardSlots::WhiteKing };
+        let checkers = attackers(board, board[king].get_lsb_index(), mover);
+        if checkers.0 != 0 {
+            entry.checks += 1;
+            // like the wiki tables a double check is not counted as a discovered check as well
+            if checkers.count_ones() > 1 { entry.double_checks += 1; }
+            else if checkers.0 != 1 << moved_square.0 { entry.discovered_checks += 1; }
+            if !has_legal_move(board) { stats[ply].checkmates += 1; }
+        }
+        if ply + 1 < stats.len() { collect_stats(board, ply + 1, stats); }
+        board.unmake_move(mov, &undo);
+    }
+}
+
+// returns the stats of depth 1 to depth
+pub fn perft_stats(board: &BoardStatus, depth: usize) -> Vec<PerftStats> {
+    let mut stats = vec![PerftStats::default(); depth];
+    let mut board = *board;
+    if depth > 0 { collect_stats(&mut board, 0, &mut stats); }
+    stats
+}
+
+pub fn print_perft_stats(stats: &[PerftStats]) {
+    println!("{:>5} {:>12} {:>10} {:>8} {:>8} {:>10} {:>10} {:>10} {:>8} {:>10}",
+        "Depth", "Nodes", "Captures", "E.p.", "Castles", "Promotions", "Checks", "Discovery", "Double", "Checkmates");
+    for (depth, entry) in stats.iter().enumerate() {
+        println!("{:>5} {:>12} {:>10} {:>8} {:>8} {:>10} {:>10} {:>10} {:>8} {:>10}", depth + 1, entry.nodes, entry.captures, entry.en_passant,
+            entry.castles, entry.promotions, entry.checks, entry.discovered_checks, entry.double_checks, entry.checkmates);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod pieces_controller;
-pub mod tables;
\ No newline at end of file
+pub mod tables;
+pub mod zobrist;
\ No newline at end of file
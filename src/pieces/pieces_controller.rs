@@ -1,28 +1,21 @@
 use crate::uci::UciInformation;
-use crate::board_components::{BitBoard, Color, Square, Direction, ChessBoard};
+use crate::board_components::{BitBoard, Color, Square, Direction};
 use crate::constants::board_constants::ASCII_PIECES;
 use crate::constants::eveluation_constants::MMV_LVA;
-use crate::constants::directions::{NORTH, SOUTH, WEST, EAST};
+use crate::constants::directions::{NORTH, SOUTH};
 use crate::constants::squares::*;
 use crate::debug::FenString;
 use crate::pieces::tables::*;
-use crate::constants::board_constants::{EMPTY_BITBOARD, RANK1, RANK2, RANK7, RANK8};
+use crate::pieces::zobrist::ZOBRIST_KEYS;
+use crate::nnue::DirtyPieces;
+use crate::constants::board_constants::{RANK1, RANK2, RANK7, RANK8};
+use std::cmp::Reverse;
 use std::mem::{transmute, MaybeUninit};
 use std::ops::{Index, IndexMut};
 
 
-static CASTLING_RIGHTS: ChessBoard<u8> = ChessBoard::from([
-   13, 15, 15, 15, 12, 15, 15, 14,
-   15, 15, 15, 15, 15, 15, 15, 15,
-   15, 15, 15, 15, 15, 15, 15, 15,
-   15, 15, 15, 15, 15, 15, 15, 15,
-   15, 15, 15, 15, 15, 15, 15, 15,
-   15, 15, 15, 15, 15, 15, 15, 15,
-   15, 15, 15, 15, 15, 15, 15, 15,
-    7, 15, 15, 15,  3, 15,  15, 11
-]);
-
 #[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum CastleSlots {
     WhiteKingSide  = 0b1,     // 1
     WhiteQueenSide = 0b10,    // 2
@@ -30,6 +23,27 @@ pub enum CastleSlots {
     BlackQueenSide = 0b1000,  // 8
 }
 
+impl CastleSlots {
+    pub const ALL: [CastleSlots; 4] = [CastleSlots::WhiteKingSide, CastleSlots::WhiteQueenSide, CastleSlots::BlackKingSide, CastleSlots::BlackQueenSide];
+
+    #[inline(always)]
+    pub fn get_index(&self) -> usize { (*self as u8).trailing_zeros() as usize }
+
+    #[inline(always)]
+    pub fn is_king_side(&self) -> bool { matches!(self, CastleSlots::WhiteKingSide | CastleSlots::BlackKingSide) }
+
+    // king and rook squares after castling
+    #[inline(always)]
+    pub fn get_targets(&self) -> (Square, Square) {
+        match self {
+            CastleSlots::WhiteKingSide  => (G1, F1),
+            CastleSlots::WhiteQueenSide => (C1, D1),
+            CastleSlots::BlackKingSide  => (G8, F8),
+            CastleSlots::BlackQueenSide => (C8, D8),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Castles(u8);
 
@@ -52,9 +66,24 @@ pub struct BoardStatus {
     enpassant: Square,
     half_move: usize,
     full_move: usize,
+    dirty: DirtyPieces,
+    mailbox: [Option<BoardSlots>; 64],
+    hash: u64,
+    // starting squares of the castling rooks in CastleSlots order, only the files change in Chess960
+    castle_rooks: [Square; 4],
+}
+
+// everything make_move overwrites that can not be worked out from the move itself
+#[derive(Clone, Copy)]
+pub struct UndoInfo {
+    pub captured:  Option<BoardSlots>,
+    pub castles:   Castles,
+    pub enpassant: Square,
+    pub half_move: usize,
+    pub hash:      u64,
 }
 
-#[repr(usize)]
+#[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BoardSlots {
     WhitePawn   = 0,
@@ -78,7 +107,7 @@ pub enum BoardSlots {
 impl BoardSlots {
     #[inline(always)]
     pub fn iterate_board_slots(start: BoardSlots, end: BoardSlots) -> impl Iterator<Item=BoardSlots> {
-        unsafe {(start as usize..=end as usize).map(|num| transmute(num))}
+        unsafe {(start as u8..=end as u8).map(|num| transmute(num))}
     }
     pub fn iterate_color_pieces(color: Color) -> impl Iterator<Item = BoardSlots> {
         match color {
@@ -118,6 +147,10 @@ impl BoardStatus {
             castles: Castles::new(),
             half_move: 0,
             full_move: 0,
+            dirty: DirtyPieces::new(),
+            mailbox: [None; 64],
+            hash: 0,
+            castle_rooks: [H1, A1, H8, A8],
         }
     }
 
@@ -133,20 +166,113 @@ impl BoardStatus {
     #[inline(always)]
     pub fn get_color(&self) -> Color { self.color }
 
+    #[inline(always)]
+    pub fn get_dirty_pieces(&self) -> &DirtyPieces { &self.dirty }
+
+    #[inline(always)]
+    pub fn get_hash(&self) -> u64 { self.hash }
+
+    #[inline(always)]
+    pub fn piece_on(&self, square: Square) -> Option<BoardSlots> { self.mailbox[square.0 as usize] }
+
+    // mailbox, color boards and hash all have to agree with the piece bitboards
+    pub fn check_consistency(&self) -> Result<(), String> {
+        let mut white = BitBoard::new();
+        let mut black = BitBoard::new();
+        for piece in BoardSlots::iterate_pieces() {
+            for other in BoardSlots::iterate_board_slots(piece, BoardSlots::BlackKing).skip(1) {
+                if self[piece].0 & self[other].0 != 0 { return Err(format!("{:?} and {:?} share a square", piece, other)); }
+            }
+            if (piece as usize) < 6 { white.0 |= self[piece].0; } else { black.0 |= self[piece].0; }
+        }
+        if self[BoardSlots::WhitePieces].0 != white.0 { return Err("white pieces board is out of sync".to_string()); }
+        if self[BoardSlots::BlackPieces].0 != black.0 { return Err("black pieces board is out of sync".to_string()); }
+        if self[BoardSlots::AllPieces].0 != white.0 | black.0 { return Err("all pieces board is out of sync".to_string()); }
+        for piece in BoardSlots::iterate_pieces() {
+            for square in self[piece] {
+                if self.piece_on(square) != Some(piece) {
+                    return Err(format!("mailbox has {:?} on {} but bitboards have {:?}", self.piece_on(square), square.get_name(), piece));
+                }
+            }
+        }
+        if self.mailbox.iter().filter(|piece| piece.is_some()).count() != self[BoardSlots::AllPieces].count_ones() as usize {
+            return Err("mailbox has pieces on empty squares".to_string());
+        }
+        if self.hash != self.compute_hash() { return Err("hash is out of sync".to_string()); }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn debug_check_consistency(&self) {
+        #[cfg(debug_assertions)]
+        if let Err(err) = self.check_consistency() { panic!("{}\n{}", err, self); }
+    }
+
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = ZOBRIST_KEYS.castles[self.castles.0 as usize];
+        for piece in BoardSlots::iterate_pieces() {
+            for square in self[piece] { hash ^= ZOBRIST_KEYS.pieces[piece as usize][square.0 as usize]; }
+        }
+        if self.enpassant != NO_SQUARE { hash ^= ZOBRIST_KEYS.enpassant[self.enpassant.get_file() as usize]; }
+        if self.color == Color::Black { hash ^= ZOBRIST_KEYS.side; }
+        hash
+    }
+
     pub fn can_castle(&self, castle: CastleSlots) -> bool {
         (self.castles.0 & (castle as u8)) != 0
     }
     pub fn from(boards: [BitBoard; 15], color: Color, enpassant: Square, castles: Castles, half_move: usize, full_move: usize) -> Self {
-        Self {boards, color, enpassant, castles, half_move, full_move}
+        let mut board = Self {boards, color, enpassant, castles, half_move, full_move, dirty: DirtyPieces::new(), mailbox: [None; 64], hash: 0, castle_rooks: [H1, A1, H8, A8]};
+        for piece in BoardSlots::iterate_pieces() {
+            for square in boards[piece as usize] { board.mailbox[square.0 as usize] = Some(piece); }
+        }
+        board.hash = board.compute_hash();
+        board
+    }
+
+    #[inline(always)]
+    pub fn get_castle_rook(&self, castle: CastleSlots) -> Square { self.castle_rooks[castle.get_index()] }
+
+    pub fn set_castle_rook(&mut self, castle: CastleSlots, square: Square) { self.castle_rooks[castle.get_index()] = square; }
+
+    // castling move of the side to move for this right, the king "captures" its own rook
+    pub fn get_castle_of_move(&self, mov: MoveBitField) -> CastleSlots {
+        let king_side = mov.get_target().get_file() > mov.get_source().get_file();
+        match (self.color, king_side) {
+            (Color::White, true)  => CastleSlots::WhiteKingSide,
+            (Color::White, false) => CastleSlots::WhiteQueenSide,
+            (Color::Black, true)  => CastleSlots::BlackKingSide,
+            (Color::Black, false) => CastleSlots::BlackQueenSide,
+        }
+    }
+
+    #[inline(always)]
+    fn update_castles(&mut self, piece: BoardSlots, source_square: Square, target_square: Square) {
+        let mut castles = self.castles.0;
+        if piece == BoardSlots::WhiteKing { castles &= !0b0011; }
+        if piece == BoardSlots::BlackKing { castles &= !0b1100; }
+        for (index, rook) in self.castle_rooks.iter().enumerate() {
+            if *rook == source_square || *rook == target_square { castles &= !(1 << index); }
+        }
+        if castles != self.castles.0 {
+            self.hash ^= ZOBRIST_KEYS.castles[self.castles.0 as usize] ^ ZOBRIST_KEYS.castles[castles as usize];
+            self.castles.0 = castles;
+        }
     }
 
     pub fn remove_castle(&mut self, castle: CastleSlots) {
-        self.castles.remove_castle(castle)
+        self.hash ^= ZOBRIST_KEYS.castles[self.castles.0 as usize];
+        self.castles.remove_castle(castle);
+        self.hash ^= ZOBRIST_KEYS.castles[self.castles.0 as usize];
     }
 
     #[inline(always)]
     pub fn change_color(&mut self) {
-        self.color = unsafe {transmute(self.color as usize ^ 1)}
+        self.color = match self.color {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        };
+        self.hash ^= ZOBRIST_KEYS.side;
     }
     
     #[inline(always)]
@@ -160,15 +286,31 @@ impl BoardStatus {
         self.boards[piece as usize].set_bit(square);
         self.get_pieces_board(piece).set_bit(square);
         self.boards[BoardSlots::AllPieces as usize].set_bit(square);
+        self.mailbox[square.0 as usize] = Some(piece);
+        self.hash ^= ZOBRIST_KEYS.pieces[piece as usize][square.0 as usize];
+        self.dirty.push(piece, square, true);
     }
 
     #[inline(always)]
     pub fn remove_piece(&mut self, piece: BoardSlots, square: Square) {
         self[piece].toggle_bit(square);
         self.get_pieces_board(piece).toggle_bit(square);
-        self[BoardSlots::AllPieces].toggle_bit(square)
+        self[BoardSlots::AllPieces].toggle_bit(square);
+        self.mailbox[square.0 as usize] = None;
+        self.hash ^= ZOBRIST_KEYS.pieces[piece as usize][square.0 as usize];
+        self.dirty.push(piece, square, false);
     }
 
+    // pieces giving check to the side to move
+    pub fn get_checkers(&self) -> BitBoard {
+        match self.color {
+            Color::White => get_attackers(self, self[BoardSlots::WhiteKing].get_lsb_index(), Color::Black),
+            Color::Black => get_attackers(self, self[BoardSlots::BlackKing].get_lsb_index(), Color::White),
+        }
+    }
+
+    pub fn is_in_check(&self) -> bool { !self.get_checkers().is_empty() }
+
     #[inline(always)]
     pub fn get_other_color(&self) -> Color {
         match self.color {
@@ -177,91 +319,105 @@ impl BoardStatus {
         }
     }
 
+    // returns None and leaves the board untouched when the move leaves the own king in check
     #[inline(always)]
-    pub fn make_move(&mut self, mov: MoveBitField) -> bool {
-        let copy_data     = *self;
+    pub fn make_move(&mut self, mov: MoveBitField) -> Option<UndoInfo> {
         let source_square = mov.get_source();
         let target_square = mov.get_target();
         let piece         = mov.get_piece();
         let promoted      = mov.get_promoted();
-        
+        let mut undo = UndoInfo { captured: None, castles: self.castles, enpassant: self.enpassant, half_move: self.half_move, hash: self.hash };
+
+        self.dirty.clear();
+        if self.enpassant != NO_SQUARE { self.hash ^= ZOBRIST_KEYS.enpassant[self.enpassant.get_file() as usize]; }
         self.enpassant = NO_SQUARE;
-        self.half_move += 1;
+        if piece == BoardSlots::WhitePawn || piece == BoardSlots::BlackPawn || mov.is_move_capture() { self.half_move = 0; }
+        else { self.half_move += 1; }
+        if self.color == Color::Black { self.full_move += 1; }
+
+        if mov.is_move_castling() {
+            let (king_target, rook_target) = self.get_castle_of_move(mov).get_targets();
+            let rook = if self.color == Color::White { BoardSlots::WhiteRook } else { BoardSlots::BlackRook };
+            self.remove_piece(piece, source_square);
+            self.remove_piece(rook, target_square);
+            self.set_piece_bit(piece, king_target);
+            self.set_piece_bit(rook, rook_target);
+        }
+        else {
+            if mov.is_move_capture() && !mov.is_move_enpassant() {
+                undo.captured = self.mailbox[target_square.0 as usize];
+                if let Some(captured) = undo.captured { self.remove_piece(captured, target_square); }
+            }
+            self.remove_piece(piece, source_square);
+            if MoveBitField::is_move_promoted(promoted) { self.set_piece_bit(promoted, target_square);}
+            else { self.set_piece_bit(piece, target_square); }
+        }
 
-        self.remove_piece(piece, source_square);
-        if MoveBitField::is_move_promoted(promoted) { self.set_piece_bit(promoted, target_square);}
-        else { self.set_piece_bit(piece, target_square); }
-        
         if mov.is_move_enpassant() {
             match self.color {
                 Color::White => self.remove_piece(BoardSlots::BlackPawn, target_square + SOUTH),
                 Color::Black => self.remove_piece(BoardSlots::WhitePawn, target_square + NORTH),
             }
-            
-        }
-        else if mov.is_move_capture() {
-            let enemey_pieces = match self.color {
-                Color::White => BoardSlots::iterate_board_slots(BoardSlots::BlackPawn, BoardSlots::BlackKing),
-                Color::Black => BoardSlots::iterate_board_slots(BoardSlots::WhitePawn, BoardSlots::WhiteKing),
-            };
-            for enemy_piece in enemey_pieces {
-                if !self[enemy_piece].is_square_set(target_square) {continue;}
-                self[enemy_piece].toggle_bit(target_square);
-                self.get_pieces_board(enemy_piece).toggle_bit(target_square);
-                break;
-            }
         }
-
         else if mov.is_move_double() {
             match self.color {
                 Color::White => self.enpassant = target_square + SOUTH,
                 Color::Black => self.enpassant = target_square + NORTH,
             }
+            self.hash ^= ZOBRIST_KEYS.enpassant[self.enpassant.get_file() as usize];
         }
-        else if mov.is_move_castling() {
-            match target_square {
-                G1 => { 
-                    self.remove_piece(BoardSlots::WhiteRook, H1);
-                    self.set_piece_bit(BoardSlots::WhiteRook, F1);
-                },
-                C1 => {
-                    self.remove_piece(BoardSlots::WhiteRook, A1);
-                    self.set_piece_bit(BoardSlots::WhiteRook, D1);
-                },
-                G8 => {
-                    self.remove_piece(BoardSlots::BlackRook, H8);
-                    self.set_piece_bit(BoardSlots::BlackRook, F8);
-                },
-                C8 => {
-                    self.remove_piece(BoardSlots::BlackRook, A8);
-                    self.set_piece_bit(BoardSlots::BlackRook, D8);
-                },
-                _  => unreachable!(),
-            }
+        self.update_castles(piece, source_square, target_square);
+        self.change_color();
+
+        let king_attacked = match self.color {
+            Color::Black => is_square_attacked_white(self, self[BoardSlots::WhiteKing].get_lsb_index()),
+            Color::White => is_square_attacked_black(self, self[BoardSlots::BlackKing].get_lsb_index()),
+        };
+        if king_attacked {
+            self.unmake_move(mov, &undo);
+            return None;
         }
-        self.castles.0 &= CASTLING_RIGHTS[source_square];
-        self.castles.0 &= CASTLING_RIGHTS[target_square];
+        self.debug_check_consistency();
+        Some(undo)
+    }
+
+    #[inline(always)]
+    pub fn unmake_move(&mut self, mov: MoveBitField, undo: &UndoInfo) {
+        let source_square = mov.get_source();
+        let target_square = mov.get_target();
+        let piece         = mov.get_piece();
+        let promoted      = mov.get_promoted();
+
         self.change_color();
-        
-        match self.color {
-            Color::Black => {
-                let square = self[BoardSlots::WhiteKing].get_lsb_index();
-                
-                if is_square_attacked_white(&self, square) {
-                    *self = copy_data;
-                    return false;
-                }
-                true
-            }
-            Color::White => {
-                let square = self[BoardSlots::BlackKing].get_lsb_index();
-                if is_square_attacked_black(&self, square) {
-                    *self = copy_data;
-                    return false;
-                }
-                true
+        if self.color == Color::Black { self.full_move -= 1; }
+
+        if mov.is_move_castling() {
+            let (king_target, rook_target) = self.get_castle_of_move(mov).get_targets();
+            let rook = if self.color == Color::White { BoardSlots::WhiteRook } else { BoardSlots::BlackRook };
+            self.remove_piece(piece, king_target);
+            self.remove_piece(rook, rook_target);
+            self.set_piece_bit(piece, source_square);
+            self.set_piece_bit(rook, target_square);
+        }
+        else {
+            if MoveBitField::is_move_promoted(promoted) { self.remove_piece(promoted, target_square); }
+            else { self.remove_piece(piece, target_square); }
+            self.set_piece_bit(piece, source_square);
+        }
+
+        if mov.is_move_enpassant() {
+            match self.color {
+                Color::White => self.set_piece_bit(BoardSlots::BlackPawn, target_square + SOUTH),
+                Color::Black => self.set_piece_bit(BoardSlots::WhitePawn, target_square + NORTH),
             }
         }
+        else if let Some(captured) = undo.captured { self.set_piece_bit(captured, target_square); }
+
+        self.castles   = undo.castles;
+        self.enpassant = undo.enpassant;
+        self.half_move = undo.half_move;
+        self.hash      = undo.hash;
+        self.debug_check_consistency();
     }
 }
 
@@ -272,7 +428,7 @@ impl std::fmt::Display for BoardStatus {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct MoveBitField(u64);
 impl MoveBitField {
     pub const NO_MOVE: MoveBitField = MoveBitField(0);
@@ -312,12 +468,6 @@ impl MoveBitField {
         self
     }
 
-    pub fn set_score(&mut self, uci_info: &UciInformation, other_color: Color) {
-        self.0 |= score_move(uci_info, *self, other_color) << 24;
-    }
-    #[inline(always)]
-    pub fn get_score(&self) -> u64 { (self.0 >> 24) & 0xffff}
-
     #[inline(always)]
     pub fn get_source(&self) -> Square { Square((self.0 & 0x3f) as u8) }
     
@@ -325,10 +475,10 @@ impl MoveBitField {
     pub fn get_target(&self) -> Square { Square(((self.0 & 0xfc0) >> 6) as u8) }
 
     #[inline(always)]
-    pub fn get_piece(&self) -> BoardSlots { unsafe { transmute( (self.0 & 0xf000) >> 12 ) } }
+    pub fn get_piece(&self) -> BoardSlots { unsafe { transmute( ((self.0 & 0xf000) >> 12) as u8 ) } }
     
     #[inline(always)]
-    pub fn get_promoted(&self) -> BoardSlots { unsafe { transmute( (self.0 & 0xf0000) >> 16 ) } }
+    pub fn get_promoted(&self) -> BoardSlots { unsafe { transmute( ((self.0 & 0xf0000) >> 16) as u8 ) } }
 
     #[inline(always)]
     pub fn is_move_promoted(mov: BoardSlots) -> bool {mov != BoardSlots::WhitePawn}
@@ -345,8 +495,17 @@ impl MoveBitField {
     #[inline(always)]
     pub fn is_move_castling(&self) -> bool { (self.0 & 0x800000) != 0 }
 
-    pub fn get_move_name(&self) -> String {
-        let mut key = format!("{}{}", self.get_source(), self.get_target());
+    pub fn get_move_name(&self) -> String { self.get_uci_name(false) }
+
+    // castling is stored as king takes rook, outside Chess960 it is written with the king's target square
+    pub fn get_uci_name(&self, chess960: bool) -> String {
+        let source = self.get_source();
+        let mut target = self.get_target();
+        if self.is_move_castling() && !chess960 {
+            let file = if target.get_file() > source.get_file() { 6 } else { 2 };
+            target = Square(source.0 - source.get_file() + file);
+        }
+        let mut key = format!("{}{}", source, target);
         let promoted = self.get_promoted();
         if MoveBitField::is_move_promoted(promoted) { key += ASCII_PIECES[promoted as usize]; }
         key.to_lowercase()
@@ -373,6 +532,78 @@ impl std::fmt::Display for MoveBitField {
     }
 }
 
+// 16 bit form for killers and other tables: source, target, promotion (knight, bishop, rook, queen) and a flag
+// (0 normal, 1 promotion, 2 enpassant, 3 castling). The piece and the capture are left to the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompactMove(u16);
+
+impl CompactMove {
+    pub const NO_MOVE: CompactMove = CompactMove(0);
+    const PROMOTION: u16 = 1;
+    const ENPASSANT: u16 = 2;
+    const CASTLING:  u16 = 3;
+
+    pub fn from_bits(bits: u16) -> Self { Self(bits) }
+    pub fn get_bits(&self) -> u16 { self.0 }
+
+    #[inline(always)]
+    pub fn get_source(&self) -> Square { Square((self.0 & 0x3f) as u8) }
+    #[inline(always)]
+    pub fn get_target(&self) -> Square { Square(((self.0 >> 6) & 0x3f) as u8) }
+    #[inline(always)]
+    fn get_flag(&self) -> u16 { self.0 >> 14 }
+
+    pub fn is_promotion(&self) -> bool { self.get_flag() == Self::PROMOTION }
+    pub fn is_enpassant(&self) -> bool { self.get_flag() == Self::ENPASSANT }
+    pub fn is_castling(&self) -> bool { self.get_flag() == Self::CASTLING }
+
+    // the white slot of the promoted piece, MoveBitField keeps the promotion that way for both colours too
+    pub fn get_promoted(&self) -> Option<BoardSlots> {
+        self.is_promotion().then(|| [BoardSlots::WhiteKnight, BoardSlots::WhiteBishop, BoardSlots::WhiteRook, BoardSlots::WhiteQueen][((self.0 >> 12) & 3) as usize])
+    }
+
+    // the pseudo legal move of the board with the same source, target and promotion
+    pub fn to_bitfield(&self, board: &BoardStatus) -> Option<MoveBitField> {
+        MoveList::generate(board).iterate_moves().find(|mov| CompactMove::from(*mov) == *self)
+    }
+
+    pub fn get_uci_name(&self, chess960: bool) -> String {
+        let source = self.get_source();
+        let mut target = self.get_target();
+        if self.is_castling() && !chess960 {
+            let file = if target.get_file() > source.get_file() { 6 } else { 2 };
+            target = Square(source.0 - source.get_file() + file);
+        }
+        let promoted = self.get_promoted().map_or("", |piece| ["n", "b", "r", "q"][piece as usize - 1]);
+        format!("{}{}{}", source, target, promoted)
+    }
+}
+
+impl From<MoveBitField> for CompactMove {
+    #[inline(always)]
+    fn from(mov: MoveBitField) -> Self {
+        let squares = mov.get_source().0 as u16 | (mov.get_target().0 as u16) << 6;
+        let promoted = mov.get_promoted();
+        let (flag, promotion) = if MoveBitField::is_move_promoted(promoted) { (Self::PROMOTION, promoted as u16 % 6 - 1) }
+            else if mov.is_move_enpassant() { (Self::ENPASSANT, 0) }
+            else if mov.is_move_castling() { (Self::CASTLING, 0) }
+            else { (0, 0) };
+        Self(squares | promotion << 12 | flag << 14)
+    }
+}
+
+// a move with its ordering score, only MoveList::new uses it to sort the moves
+#[derive(Clone, Copy)]
+pub struct ScoredMove {
+    pub mov:   MoveBitField,
+    pub score: u64,
+}
+
+impl ScoredMove {
+    #[inline(always)]
+    pub fn new(uci_info: &UciInformation, mov: MoveBitField) -> Self { Self { mov, score: score_move(uci_info, mov) } }
+}
+
 #[derive(Clone, Copy)]
 pub struct MoveList {
     moves: [MaybeUninit<MoveBitField>; 256],
@@ -394,41 +625,55 @@ impl std::fmt::Display for MoveList {
 
 
 
+// is a white square attacked by black
 #[inline(always)]
-pub fn is_square_attacked_black(board_status: &BoardStatus, square: Square) -> bool {
-    let knight_attack  = generate_knight_attacks(square);
-    let king_attack    = generate_king_attacks(square);
-    let bishop_attacks = generate_bishop_attacks(square, board_status[BoardSlots::AllPieces]);
-    let rook_attacks   = generate_rook_attakcs(square,   board_status[BoardSlots::AllPieces]);
-    let queen_attacks  = bishop_attacks | rook_attacks;
-    (genereate_pawn_attacks(square, Color::Black) & board_status[BoardSlots::WhitePawn] |
-    knight_attack  & board_status[BoardSlots::WhiteKnight] |
-    bishop_attacks & board_status[BoardSlots::WhiteBishop] |
-    rook_attacks   & board_status[BoardSlots::WhiteRook]   |
-    queen_attacks  & board_status[BoardSlots::WhiteQueen]  |
-    king_attack    & board_status[BoardSlots::WhiteKing]) != EMPTY_BITBOARD
+pub fn is_square_attacked_white(board_status: &BoardStatus, square: Square) -> bool {
+    !get_attackers(board_status, square, Color::Black).is_empty()
+}
 
+// is a black square attacked by white
+#[inline(always)]
+pub fn is_square_attacked_black(board_status: &BoardStatus, square: Square) -> bool {
+    !get_attackers(board_status, square, Color::White).is_empty()
 }
+
+// pieces of the given color attacking the square
 #[inline(always)]
-pub fn is_square_attacked_white(board_status: &BoardStatus, square: Square) -> bool {
-    let knight_attack  = generate_knight_attacks(square);
-    let king_attack    = generate_king_attacks(square);
+pub fn get_attackers(board_status: &BoardStatus, square: Square, color: Color) -> BitBoard {
+    let ([pawns, knights, bishops, rooks, queens, king], defender) = match color {
+        Color::White => ([BoardSlots::WhitePawn, BoardSlots::WhiteKnight, BoardSlots::WhiteBishop,
+            BoardSlots::WhiteRook, BoardSlots::WhiteQueen, BoardSlots::WhiteKing], Color::Black),
+        Color::Black => ([BoardSlots::BlackPawn, BoardSlots::BlackKnight, BoardSlots::BlackBishop,
+            BoardSlots::BlackRook, BoardSlots::BlackQueen, BoardSlots::BlackKing], Color::White),
+    };
     let bishop_attacks = generate_bishop_attacks(square, board_status[BoardSlots::AllPieces]);
     let rook_attacks   = generate_rook_attakcs(square,   board_status[BoardSlots::AllPieces]);
-    let queen_attacks  = bishop_attacks | rook_attacks;
-    (genereate_pawn_attacks(square, Color::White) & board_status[BoardSlots::BlackPawn] |
-    knight_attack  & board_status[BoardSlots::BlackKnight] |
-    bishop_attacks & board_status[BoardSlots::BlackBishop] |
-    rook_attacks   & board_status[BoardSlots::BlackRook]   |
-    queen_attacks  & board_status[BoardSlots::BlackQueen]  |
-    king_attack    & board_status[BoardSlots::BlackKing]) != EMPTY_BITBOARD
+    genereate_pawn_attacks(square, defender) & board_status[pawns]   |
+    generate_knight_attacks(square)          & board_status[knights] |
+    bishop_attacks                           & board_status[bishops] |
+    rook_attacks                             & board_status[rooks]   |
+    (bishop_attacks | rook_attacks)          & board_status[queens]  |
+    generate_king_attacks(square)            & board_status[king]
 }
 
 impl MoveList {
     #[inline(always)]
     pub fn new(uci_info: &UciInformation) -> Self {
+        let mut res = Self::generate(&uci_info.board);
+        let mut scored = [MaybeUninit::<ScoredMove>::uninit(); 256];
+        for (slot, mov) in scored.iter_mut().zip(res.iterate_moves()) { slot.write(ScoredMove::new(uci_info, mov)); }
+        let count = res.count;
+        unsafe {
+            scored[0..count].sort_by_key(|mov| Reverse(mov.assume_init().score));
+            for (slot, mov) in res.moves.iter_mut().zip(&scored[0..count]) { slot.write(mov.assume_init().mov); }
+        }
+        res
+    }
+
+    // pseudo legal moves in generation order, without scores
+    #[inline(always)]
+    pub fn generate(board_status: &BoardStatus) -> Self {
         let mut res =  unsafe { Self { moves: MaybeUninit::uninit().assume_init(), count: 0, }};
-        let board_status = &uci_info.board;
         match board_status.get_color() {
             Color::White => {
                 res.generate_pawn_moves(&board_status, NORTH, RANK2, RANK8, BoardSlots::WhitePawn, Color::Black, BoardSlots::WhiteQueen, BoardSlots::WhiteRook, BoardSlots::WhiteBishop, BoardSlots::WhiteKnight, BoardSlots::BlackPieces);
@@ -436,7 +681,7 @@ impl MoveList {
                 res.generate_slider_moves(generate_bishop_attacks, board_status, BoardSlots::WhiteBishop, BoardSlots::WhitePieces, BoardSlots::BlackPieces);
                 res.generate_slider_moves(generate_rook_attakcs,   board_status, BoardSlots::WhiteRook,   BoardSlots::WhitePieces, BoardSlots::BlackPieces);
                 res.generate_slider_moves(generate_queen_attacks,  board_status, BoardSlots::WhiteQueen,  BoardSlots::WhitePieces, BoardSlots::BlackPieces);
-                res.generate_king_moves(board_status, BoardSlots::WhiteKing, BoardSlots::WhitePieces, BoardSlots::BlackPieces, E1, CastleSlots::WhiteKingSide, CastleSlots::WhiteQueenSide, is_square_attacked_white);
+                res.generate_king_moves(board_status, BoardSlots::WhiteKing, BoardSlots::WhitePieces, BoardSlots::BlackPieces, CastleSlots::WhiteKingSide, CastleSlots::WhiteQueenSide, is_square_attacked_white);
             }
             Color::Black => {
                 res.generate_pawn_moves(&board_status, SOUTH, RANK7, RANK1, BoardSlots::BlackPawn, Color::White, BoardSlots::BlackQueen, BoardSlots::BlackRook, BoardSlots::BlackBishop, BoardSlots::BlackKnight, BoardSlots::WhitePieces);
@@ -444,16 +689,9 @@ impl MoveList {
                 res.generate_slider_moves(generate_bishop_attacks, board_status, BoardSlots::BlackBishop, BoardSlots::BlackPieces, BoardSlots::WhitePieces);
                 res.generate_slider_moves(generate_rook_attakcs,   board_status, BoardSlots::BlackRook,   BoardSlots::BlackPieces, BoardSlots::WhitePieces);
                 res.generate_slider_moves(generate_queen_attacks,  board_status, BoardSlots::BlackQueen,  BoardSlots::BlackPieces, BoardSlots::WhitePieces);
-                res.generate_king_moves(board_status, BoardSlots::BlackKing, BoardSlots::BlackPieces, BoardSlots::WhitePieces, E8, CastleSlots::BlackKingSide, CastleSlots::BlackQueenSide, is_square_attacked_black);
+                res.generate_king_moves(board_status, BoardSlots::BlackKing, BoardSlots::BlackPieces, BoardSlots::WhitePieces, CastleSlots::BlackKingSide, CastleSlots::BlackQueenSide, is_square_attacked_black);
             }
         }
-        let other_color = board_status.get_other_color();
-        unsafe {
-            res.moves[0..res.count].iter_mut().for_each(|mov| {
-                mov.assume_init_mut().set_score(uci_info, other_color);
-            });
-            res.moves[0..res.count].sort_by(|mov1, mov2| mov2.assume_init().get_score().cmp(&mov1.assume_init().get_score()));
-        }
         res
     }
     
@@ -535,20 +773,20 @@ impl MoveList {
     }
     #[inline(always)]
     fn generate_king_moves(&mut self, board_status: &BoardStatus, piece: BoardSlots, my_pieces: BoardSlots, enemy_pieces: BoardSlots,
-    king_pos: Square, king_side_castle: CastleSlots, queen_side_castle: CastleSlots, is_square_attacked: fn(&BoardStatus, Square) -> bool) {
+    king_side_castle: CastleSlots, queen_side_castle: CastleSlots, is_square_attacked: fn(&BoardStatus, Square) -> bool) {
         
         self.generate_non_sliding_moves(generate_king_attacks, board_status, piece, my_pieces, enemy_pieces);
-        let board = board_status[BoardSlots::AllPieces];
-        if !is_square_attacked(board_status, king_pos) {
-            if board_status.can_castle(king_side_castle) && !is_square_attacked(board_status, king_pos + EAST) && !board.is_square_set(king_pos + EAST) && !board.is_square_set(king_pos + EAST * 2) {
-                self.append_move(MoveBitField::new(piece, king_pos, king_pos + EAST * 2).set_castling());
-            }
-            if board_status.can_castle(queen_side_castle) && !is_square_attacked(board_status, king_pos + WEST) && 
-            !board.is_square_set(king_pos + WEST) && !board.is_square_set(king_pos + WEST * 2) && !board.is_square_set(king_pos + WEST * 3) {
-                self.append_move(MoveBitField::new(piece, king_pos, king_pos + WEST * 2).set_castling())
-            }
+        let king_square = board_status[piece].get_lsb_index();
+        for castle in [king_side_castle, queen_side_castle] {
+            if !board_status.can_castle(castle) { continue; }
+            let rook_square = board_status.get_castle_rook(castle);
+            let (king_target, rook_target) = castle.get_targets();
+            // the king and the castling rook do not block each other, everything else on both paths does
+            let blockers = board_status[BoardSlots::AllPieces].0 & !(1 << king_square.0) & !(1 << rook_square.0);
+            if blockers & (rank_span(king_square, king_target) | rank_span(rook_square, rook_target)) != 0 { continue; }
+            if BitBoard(rank_span(king_square, king_target)).into_iter().any(|square| is_square_attacked(board_status, square)) { continue; }
+            self.append_move(MoveBitField::new(piece, king_square, rook_square).set_castling());
         }
-
     }
 
     pub fn iterate_moves<'a>(&'a self) -> impl Iterator<Item = MoveBitField> + 'a {
@@ -565,38 +803,54 @@ impl Index<usize> for MoveList {
 }
 
 
+// squares from a to b on the same rank, both ends included
 #[inline(always)]
-fn score_move(uci_info: &UciInformation, mov: MoveBitField, enemy_color: Color) -> u64 {
+fn rank_span(a: Square, b: Square) -> u64 {
+    let (low, high) = (u8::min(a.0, b.0), u8::max(a.0, b.0));
+    (u64::MAX >> (63 - high)) & (u64::MAX << low)
+}
+
+#[inline(always)]
+fn score_move(uci_info: &UciInformation, mov: MoveBitField) -> u64 {
     let current_piece = mov.get_piece();
     let target_square = mov.get_target();
     if mov.is_move_capture() { 
-        for enemy_piece in BoardSlots::iterate_color_pieces(enemy_color) {
-            if uci_info.board[enemy_piece].is_square_set(target_square) {
-                return MMV_LVA[(current_piece, enemy_piece)];
-            }
+        if let Some(enemy_piece) = uci_info.board.piece_on(target_square) {
+            return MMV_LVA[(current_piece, enemy_piece)];
         }
     }
-    if mov == uci_info.board_history.killer_moves[uci_info.board.half_move][0] { return 9000; }
-    if mov == uci_info.board_history.killer_moves[uci_info.board.half_move][1] { return 8000; }
+    if let Some(killers) = uci_info.board_history.killer_moves.get(uci_info.ply) {
+        let mov = CompactMove::from(mov);
+        if mov == killers[0] { return 9000; }
+        if mov == killers[1] { return 8000; }
+    }
     return 0;
 }
 
 #[derive(Clone, Copy)]
 pub struct BoardHistory {
-    pub killer_moves    :   [[MoveBitField; 2]; 64],
+    pub killer_moves    :   [[CompactMove; 2]; 64],
     pub found_best_move :   MoveBitField,
+    pub found_best_score:   isize,
+    // last completed iteration, a search stopped early keeps the result of this depth
+    pub found_best_depth:   isize,
 }
 
 
 impl BoardHistory {
     pub fn new () -> Self {
-        Self {killer_moves: [[MoveBitField::NO_MOVE; 2]; 64], found_best_move: MoveBitField::NO_MOVE}
+        Self {killer_moves: [[CompactMove::NO_MOVE; 2]; 64], found_best_move: MoveBitField::NO_MOVE, found_best_score: 0, found_best_depth: 0}
     }
     
-    pub fn append_killer_move(&mut self, mov: MoveBitField, half_move: usize) {
-        self.killer_moves[half_move][1] = self.killer_moves[half_move][0];
-        self.killer_moves[half_move][0] = mov;
+    pub fn append_killer_move(&mut self, mov: MoveBitField, ply: usize) {
+        if ply >= self.killer_moves.len() { return; }
+        self.killer_moves[ply][1] = self.killer_moves[ply][0];
+        self.killer_moves[ply][0] = CompactMove::from(mov);
     }
 
-    pub fn add_new_best_move(&mut self, mov: MoveBitField) { self.found_best_move = mov; }
+    pub fn add_new_best_move(&mut self, mov: MoveBitField, score: isize, depth: isize) {
+        self.found_best_move = mov;
+        self.found_best_score = score;
+        self.found_best_depth = depth;
+    }
 }
\ No newline at end of file
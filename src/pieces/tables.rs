@@ -1,56 +1,275 @@
-use crate::constants::directions::*;
 use crate::constants::board_constants::*;
 use crate::board_components::{BitBoard, Square, ChessBoard, MagicNum, Color, MagicNumGenerator};
-use crate::constants::board_constants::{BISHOP_MAX_BLOCK_PERM, ROOK_MAX_BLOCK_PERM, create_bishop_move_counts, create_bishop_magics, create_rook_move_counts, create_rook_magics};
-use std::cmp::{min, max};
-use std::sync::Once;
+use crate::constants::board_constants::{create_bishop_move_counts, create_bishop_magics, create_rook_move_counts, create_rook_magics};
 
-static mut BISHOP_TABLE: ChessBoard<[BitBoard; BISHOP_MAX_BLOCK_PERM]> = ChessBoard::from([[BitBoard::new(); BISHOP_MAX_BLOCK_PERM]; 64]);
-static mut BISHOP_ATTACKS: ChessBoard<BitBoard> = ChessBoard::from([BitBoard::new(); 64]);
+// Every table is built at compile time from the magic numbers in constants, so lookups need no setup
+// and no unsafe. The builders work on plain u64 because trait operators are not usable in const fn.
+
+// directions as (rank step, file step), the first four raise the square index and the last four lower it
+const DIRECTIONS: [(i8, i8); 8] = [(0, 1), (1, -1), (1, 0), (1, 1), (0, -1), (-1, 1), (-1, 0), (-1, -1)];
+const BISHOP_DIRECTIONS: [usize; 4] = [1, 3, 5, 7];
+const ROOK_DIRECTIONS:   [usize; 4] = [0, 2, 4, 6];
+const KING_OFFSETS:      [(i8, i8); 8] = DIRECTIONS;
+const KNIGHT_OFFSETS:    [(i8, i8); 8] = [(2, 1), (2, -1), (1, 2), (1, -2), (-1, 2), (-1, -2), (-2, 1), (-2, -1)];
+
+static RAYS: [[u64; 64]; 8] = create_rays();
+
+// the squares share one table, each one takes 2^move count entries from its offset
+const BISHOP_TABLE_SIZE: usize = get_table_size(&create_bishop_move_counts());
+static BISHOP_TABLE: [BitBoard; BISHOP_TABLE_SIZE] = create_slider_table(&BISHOP_DIRECTIONS, &BISHOP_MAGICS, &BISHOP_MOVES_COUNTS);
+static BISHOP_OFFSETS: ChessBoard<usize> = get_table_offsets(&create_bishop_move_counts());
+static BISHOP_ATTACKS: ChessBoard<BitBoard> = create_slider_masks(&BISHOP_DIRECTIONS);
 static BISHOP_MOVES_COUNTS: ChessBoard<u64> = create_bishop_move_counts();
 static BISHOP_MAGICS: ChessBoard<MagicNum> = create_bishop_magics();
 
-static mut ROOK_TABLE: ChessBoard<[BitBoard; ROOK_MAX_BLOCK_PERM]> = ChessBoard::from([[BitBoard::new(); ROOK_MAX_BLOCK_PERM]; 64]);
-static mut ROOK_ATTACKS: ChessBoard<BitBoard> = ChessBoard::from([BitBoard::new(); 64]);
+const ROOK_TABLE_SIZE: usize = get_table_size(&create_rook_move_counts());
+static ROOK_TABLE: [BitBoard; ROOK_TABLE_SIZE] = create_slider_table(&ROOK_DIRECTIONS, &ROOK_MAGICS, &ROOK_MOVES_COUNTS);
+static ROOK_OFFSETS: ChessBoard<usize> = get_table_offsets(&create_rook_move_counts());
+static ROOK_ATTACKS: ChessBoard<BitBoard> = create_slider_masks(&ROOK_DIRECTIONS);
 static ROOK_MOVES_COUNTS: ChessBoard<u64> = create_rook_move_counts();
 static ROOK_MAGICS: ChessBoard<MagicNum> = create_rook_magics();
 
-static mut KING_TABLE: ChessBoard<BitBoard> = ChessBoard::from([BitBoard::new(); 64]);
-static mut KNIGHT_TABLE: ChessBoard<BitBoard> = ChessBoard::from([BitBoard::new(); 64]);
+// pext indexes with every bit of the mask, so these use the mask sizes and not the move counts
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+static BISHOP_PEXT_TABLE: [BitBoard; get_table_size(&create_mask_sizes(&BISHOP_DIRECTIONS))] = create_pext_table(&BISHOP_DIRECTIONS);
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+static BISHOP_PEXT_OFFSETS: ChessBoard<usize> = get_table_offsets(&create_mask_sizes(&BISHOP_DIRECTIONS));
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+static ROOK_PEXT_TABLE: [BitBoard; get_table_size(&create_mask_sizes(&ROOK_DIRECTIONS))] = create_pext_table(&ROOK_DIRECTIONS);
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+static ROOK_PEXT_OFFSETS: ChessBoard<usize> = get_table_offsets(&create_mask_sizes(&ROOK_DIRECTIONS));
 
-static mut PAWN_TABLE: [ChessBoard<BitBoard>; 2] = [ChessBoard::from([BitBoard::new(); 64]), ChessBoard::from([BitBoard::new(); 64])];
+pub(crate) static BETWEEN_TABLE: [[BitBoard; 64]; 64] = create_between_table();
+pub(crate) static LINE_TABLE: [[BitBoard; 64]; 64] = create_line_table();
 
-static INIT: Once = Once::new();
+static KING_TABLE: ChessBoard<BitBoard> = create_step_table(&KING_OFFSETS);
+static KNIGHT_TABLE: ChessBoard<BitBoard> = create_step_table(&KNIGHT_OFFSETS);
 
-pub fn init_statics() {
-    INIT.call_once( || {
-        initialize_bishop_components();
-        initialize_rook_components();
-        initialize_pawn_table();
-        initialize_knight_table();
-        initialize_king_table();
-    });
+static PAWN_TABLE: [ChessBoard<BitBoard>; 2] = [create_step_table(&[(1, -1), (1, 1)]), create_step_table(&[(-1, -1), (-1, 1)])];
+
+const fn offset_square(square: usize, rank_step: i8, file_step: i8) -> Option<usize> {
+    let rank = (square / 8) as i8 + rank_step;
+    let file = (square % 8) as i8 + file_step;
+    if rank < 0 || rank > 7 || file < 0 || file > 7 { None } else { Some((rank * 8 + file) as usize) }
+}
+
+const fn step_attacks(square: usize, offsets: &[(i8, i8)]) -> u64 {
+    let mut attacks = 0;
+    let mut index = 0;
+    while index < offsets.len() {
+        if let Some(target) = offset_square(square, offsets[index].0, offsets[index].1) { attacks |= 1 << target; }
+        index += 1;
+    }
+    attacks
 }
 
+// every square from the square to the edge in one direction
+const fn create_rays() -> [[u64; 64]; 8] {
+    let mut rays = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut square = 0;
+        while square < 64 {
+            let mut current = square;
+            while let Some(target) = offset_square(current, DIRECTIONS[direction].0, DIRECTIONS[direction].1) {
+                rays[direction][square] |= 1 << target;
+                current = target;
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    rays
+}
+
+// the opposite of a direction is four places further in DIRECTIONS
+const fn create_between_table() -> [[BitBoard; 64]; 64] {
+    let rays = create_rays();
+    let mut table = [[BitBoard(0); 64]; 64];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut first = 0;
+        while first < 64 {
+            let mut ray = rays[direction][first];
+            while ray != 0 {
+                let second = ray.trailing_zeros() as usize;
+                table[first][second] = BitBoard(rays[direction][first] & rays[(direction + 4) % 8][second]);
+                ray &= ray - 1;
+            }
+            first += 1;
+        }
+        direction += 1;
+    }
+    table
+}
+
+const fn create_line_table() -> [[BitBoard; 64]; 64] {
+    let rays = create_rays();
+    let mut table = [[BitBoard(0); 64]; 64];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut first = 0;
+        while first < 64 {
+            let line = rays[direction][first] | rays[(direction + 4) % 8][first] | 1 << first;
+            let mut ray = rays[direction][first];
+            while ray != 0 {
+                table[first][ray.trailing_zeros() as usize] = BitBoard(line);
+                ray &= ray - 1;
+            }
+            first += 1;
+        }
+        direction += 1;
+    }
+    table
+}
+
+// a ray is cut behind its first blocker, the blocker itself is attacked
+const fn slider_attacks(square: usize, blockers: u64, directions: &[usize; 4], rays: &[[u64; 64]; 8]) -> u64 {
+    let mut attacks = 0;
+    let mut index = 0;
+    while index < 4 {
+        let direction = directions[index];
+        let ray = rays[direction][square];
+        let ray_blockers = ray & blockers;
+        attacks |= if ray_blockers == 0 { ray } else {
+            let first = if direction < 4 { ray_blockers.trailing_zeros() } else { 63 - ray_blockers.leading_zeros() };
+            ray ^ rays[direction][first as usize]
+        };
+        index += 1;
+    }
+    attacks
+}
+
+// the relevant occupancy: every ray without its last square, a piece there can not block anything
+const fn slider_mask(square: usize, directions: &[usize; 4], rays: &[[u64; 64]; 8]) -> u64 {
+    let mut mask = 0;
+    let mut index = 0;
+    while index < 4 {
+        let ray = rays[directions[index]][square];
+        if ray != 0 {
+            let last = if directions[index] < 4 { 63 - ray.leading_zeros() } else { ray.trailing_zeros() };
+            mask |= ray ^ 1 << last;
+        }
+        index += 1;
+    }
+    mask
+}
+
+// spreads the bits of index over the set bits of mask
 fn get_possible_occupancy(bitboard: BitBoard, index: u64) -> BitBoard {
-    let mut occupancy =  BitBoard::new();
-    
+    let mut occupancy = BitBoard::new();
     for (count, square) in bitboard.enumerate() {
-        if (index & 1 << count) != 0 {
-            occupancy.set_bit(square);
+        if (index & 1 << count) != 0 { occupancy.set_bit(square); }
+    }
+    occupancy
+}
+
+const fn create_step_table(offsets: &[(i8, i8)]) -> ChessBoard<BitBoard> {
+    let mut table = [BitBoard(0); 64];
+    let mut square = 0;
+    while square < 64 {
+        table[square] = BitBoard(step_attacks(square, offsets));
+        square += 1;
+    }
+    ChessBoard::from(table)
+}
+
+const fn create_slider_masks(directions: &[usize; 4]) -> ChessBoard<BitBoard> {
+    let rays = create_rays();
+    let mut masks = [BitBoard(0); 64];
+    let mut square = 0;
+    while square < 64 {
+        masks[square] = BitBoard(slider_mask(square, directions, &rays));
+        square += 1;
+    }
+    ChessBoard::from(masks)
+}
+
+pub const fn get_table_size(move_counts: &ChessBoard<u64>) -> usize {
+    let (mut size, mut square) = (0, 0);
+    while square < 64 {
+        size += 1 << move_counts.0[square];
+        square += 1;
+    }
+    size
+}
+
+const fn get_table_offsets(move_counts: &ChessBoard<u64>) -> ChessBoard<usize> {
+    let mut offsets = [0; 64];
+    let mut square = 1;
+    while square < 64 {
+        offsets[square] = offsets[square - 1] + (1 << move_counts.0[square - 1]);
+        square += 1;
+    }
+    ChessBoard::from(offsets)
+}
+
+// occupancies are walked with the carry rippler trick, (occupancy - mask) & mask gives the next subset of mask
+const fn create_slider_table<const LEN: usize>(directions: &[usize; 4], magics: &ChessBoard<MagicNum>, move_counts: &ChessBoard<u64>) -> [BitBoard; LEN] {
+    let rays = create_rays();
+    let offsets = get_table_offsets(move_counts);
+    let mut table = [BitBoard(0); LEN];
+    let mut square = 0;
+    while square < 64 {
+        let mask = slider_mask(square, directions, &rays);
+        let (magic, shift) = (magics.0[square].0, 64 - move_counts.0[square]);
+        let mut occupancy: u64 = 0;
+        loop {
+            table[offsets.0[square] + (occupancy.wrapping_mul(magic) >> shift) as usize] = BitBoard(slider_attacks(square, occupancy, directions, &rays));
+            occupancy = occupancy.wrapping_sub(mask) & mask;
+            if occupancy == 0 { break; }
         }
+        square += 1;
+    }
+    table
+}
+
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+const fn create_mask_sizes(directions: &[usize; 4]) -> ChessBoard<u64> {
+    let rays = create_rays();
+    let mut sizes = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        sizes[square] = slider_mask(square, directions, &rays).count_ones() as u64;
+        square += 1;
     }
-    return occupancy;
+    ChessBoard::from(sizes)
+}
+
+// the carry rippler walks the subsets in the order of their pext index, so the index is just a counter
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+const fn create_pext_table<const LEN: usize>(directions: &[usize; 4]) -> [BitBoard; LEN] {
+    let rays = create_rays();
+    let mut table = [BitBoard(0); LEN];
+    let (mut square, mut index) = (0, 0);
+    while square < 64 {
+        let mask = slider_mask(square, directions, &rays);
+        let mut occupancy: u64 = 0;
+        loop {
+            table[index] = BitBoard(slider_attacks(square, occupancy, directions, &rays));
+            occupancy = occupancy.wrapping_sub(mask) & mask;
+            index += 1;
+            if occupancy == 0 { break; }
+        }
+        square += 1;
+    }
+    table
 }
 
 pub fn find_magic_number(mask_attacks: fn(Square) -> BitBoard, attack_on_fly: fn(Square, BitBoard) -> BitBoard, square: Square) -> MagicNum {
+    let bits = mask_attacks(square).count_ones();
+    search_magic_number(mask_attacks, attack_on_fly, square, bits, &mut MagicNumGenerator::new(), 1000000000).unwrap()
+}
+
+// a magic with fewer bits than the mask needs only exists when enough occupancies share their attacks,
+// so the search gives up after the given tries
+pub fn search_magic_number(mask_attacks: fn(Square) -> BitBoard, attack_on_fly: fn(Square, BitBoard) -> BitBoard, square: Square,
+    bits: u32, magic_num_generator: &mut MagicNumGenerator, tries: usize) -> Option<MagicNum> {
     let attack_mask = mask_attacks(square);
     let move_count = attack_mask.count_ones();
     let mut occupancies:  [BitBoard; 4096] = [BitBoard::new(); 4096];
     let mut used_attacks: [BitBoard; 4096] = [BitBoard::new(); 4096];
     let mut attacks:      [BitBoard; 4096] = [BitBoard::new(); 4096];
-    
-    let mut magic_num_generator = MagicNumGenerator::new();
 
     let total_mask_pos: usize = (2_usize).pow(move_count as u32);
     (0..total_mask_pos).for_each(|index| {
@@ -58,223 +277,148 @@ pub fn find_magic_number(mask_attacks: fn(Square) -> BitBoard, attack_on_fly: fn
         attacks[index] = attack_on_fly(square, occupancies[index]);
     });
 
-    'start: for _ in 0..1000000000 {
+    'start: for _ in 0..tries {
         let magic_num = magic_num_generator.gen();
         if ((attack_mask * magic_num) & 0xFF00000000000000).count_ones() < 6 {continue;}
-        for el in used_attacks.iter_mut() {*el = BitBoard::new();}
+        for el in used_attacks.iter_mut().take(1 << bits) {*el = BitBoard::new();}
         for index in 0..total_mask_pos {
-            let magic_index = ((occupancies[index] * magic_num) >> (64 - move_count)) as usize;
-            
+            let magic_index = ((occupancies[index] * magic_num) >> (64 - bits as u64)) as usize;
+
             if used_attacks[magic_index] == EMPTY_BITBOARD { used_attacks[magic_index] = attacks[index];}
             else if used_attacks[magic_index] != attacks[index] {continue 'start;}
         }
-        return magic_num;
+        return Some(magic_num);
     }
-    
-  unreachable!()
+    None
 }
 
-fn initialize_slider_table<const LEN: usize>(table: &mut ChessBoard<[BitBoard; LEN]>,  magics: &ChessBoard<MagicNum>, mask_attacks: fn(Square) -> BitBoard, attack_on_fly: fn(Square, BitBoard) -> BitBoard) {
-    for square in Square::create_squares(0, 64) {
-        let attack = mask_attacks(square);
-        let move_count = attack.count_ones();
-        let total_mask_pos = 2_u64.pow(move_count);
-        for index in 0..total_mask_pos {
-            let occupancy = get_possible_occupancy(attack, index);
-            let magic_index = (occupancy * magics[square]) >> (64 - move_count);
-            table[square][magic_index as usize] = attack_on_fly(square, occupancy);
+// checks every occupancy of the mask, two occupancies may only share an index when their attacks are the same
+pub fn verify_magic_number(mask_attacks: fn(Square) -> BitBoard, attack_on_fly: fn(Square, BitBoard) -> BitBoard, square: Square,
+    magic_num: MagicNum, bits: u32) -> bool {
+    let mask = mask_attacks(square).0;
+    let mut used_attacks: Vec<Option<BitBoard>> = vec![None; 1 << bits];
+    let mut occupancy: u64 = 0;
+    loop {
+        let magic_index = ((BitBoard(occupancy) * magic_num) >> (64 - bits as u64)) as usize;
+        let attacks = attack_on_fly(square, BitBoard(occupancy));
+        match used_attacks[magic_index] {
+            Some(used) if used != attacks => return false,
+            _ => used_attacks[magic_index] = Some(attacks),
         }
+        occupancy = occupancy.wrapping_sub(mask) & mask;
+        if occupancy == 0 { return true; }
     }
 }
 
-fn initialize_slider_attacks(mask_attacks: fn(Square) -> BitBoard, attacks: &mut ChessBoard<BitBoard>) {
-    for square in Square::create_squares(0, 64) {attacks[square] = mask_attacks(square);}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SliderBackend {
+    Magic,
+    Pext,
 }
 
-#[inline(always)]
-fn generate_slider_moves<const LEN: usize> (square: Square, board: BitBoard, attacks: &ChessBoard<BitBoard>, 
-    magics: &ChessBoard<MagicNum>, table: &ChessBoard<[BitBoard; LEN]>, move_counts: &ChessBoard<u64>) -> BitBoard {
-    let occupancy = board & attacks[square];
-    let magic_index = (occupancy * magics[square]) >> (64 - move_counts[square]);
-    table[square][magic_index as usize]
+impl SliderBackend {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            SliderBackend::Magic => "magic",
+            SliderBackend::Pext  => "pext",
+        }
+    }
 }
 
-fn mask_bishop_attacks(square: Square) -> BitBoard {
-    bishop_attacks_on_fly(square, BitBoard::new()) & !EDGES
+// the backend is picked at build time: pext needs the feature and BMI2 enabled for the target
+// (-C target-cpu=native or -C target-feature=+bmi2) so the lookup inlines without a runtime check
+pub const fn is_pext_available() -> bool {
+    cfg!(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))
 }
 
-fn bishop_attacks_on_fly(square: Square, blocker: BitBoard) -> BitBoard {
-    let rank = square.get_rank();
-    let file = square.get_file();
-    let mut attacks = BitBoard::new();
+#[cfg(all(feature = "pext", not(all(target_arch = "x86_64", target_feature = "bmi2"))))]
+compile_error!("the pext feature needs BMI2 enabled for the target, build with RUSTFLAGS=\"-C target-cpu=native\" or \"-C target-feature=+bmi2\"");
 
-    for i in 1..8 - max(rank, file) {
-        attacks.set_bit(square + NORTH_EAST * i);
-        if blocker.is_square_set(square + NORTH_EAST * i) {break;}
-    }
-    for i in 1..min(rank, file) + 1 {
-        attacks.set_bit(square + SOUTH_WEST * i);
-        if blocker.is_square_set(square + SOUTH_WEST * i) {break;}
-    }
-
-    for i in 1..min(8 - rank, file + 1) {
-        attacks.set_bit(square + NORTH_WEST * i);
-        if blocker.is_square_set(square + NORTH_WEST * i) {break;}
-    }
-    
-    for i in 1..min(rank + 1, 8 - file) {
-        attacks.set_bit(square + SOUTH_EAST * i);
-        if blocker.is_square_set(square + SOUTH_EAST * i) {break;}
-    }
+// a build with BMI2 enabled can not run on a CPU without it, the binaries check this before anything else
+pub fn check_cpu_features() -> Result<(), String> {
+    #[cfg(all(target_arch = "x86_64", target_feature = "bmi2"))]
+    if !std::is_x86_feature_detected!("bmi2") { return Err("this build needs a CPU with BMI2, rebuild without -C target-cpu=native or -C target-feature=+bmi2".to_string()); }
+    Ok(())
+}
 
-    attacks
+pub fn get_slider_backend() -> SliderBackend {
+    if is_pext_available() { SliderBackend::Pext } else { SliderBackend::Magic }
 }
 
 #[inline(always)]
-pub fn generate_bishop_attacks(square: Square, board: BitBoard) -> BitBoard {
-    unsafe {generate_slider_moves(square, board, &BISHOP_ATTACKS, &BISHOP_MAGICS, &BISHOP_TABLE, &BISHOP_MOVES_COUNTS)}
+fn generate_slider_moves(square: Square, board: BitBoard, attacks: &ChessBoard<BitBoard>, magics: &ChessBoard<MagicNum>,
+    table: &[BitBoard], offsets: &ChessBoard<usize>, move_counts: &ChessBoard<u64>) -> BitBoard {
+    let occupancy = board & attacks[square];
+    let magic_index = (occupancy * magics[square]) >> (64 - move_counts[square]);
+    table[offsets[square] + magic_index as usize]
 }
 
-fn initialize_bishop_components() {
-    unsafe {initialize_slider_table(&mut BISHOP_TABLE, &BISHOP_MAGICS, mask_bishop_attacks, bishop_attacks_on_fly);}
-    unsafe {initialize_slider_attacks(mask_bishop_attacks, &mut BISHOP_ATTACKS)}
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+#[inline(always)]
+fn generate_pext_moves(square: Square, board: BitBoard, attacks: &ChessBoard<BitBoard>, table: &[BitBoard], offsets: &ChessBoard<usize>) -> BitBoard {
+    // bmi2 is enabled for the whole build, see is_pext_available
+    table[offsets[square] + unsafe { std::arch::x86_64::_pext_u64(board.0, attacks[square].0) } as usize]
 }
 
-fn initialize_king_table() {
-    for square in Square::create_squares(0, 64) {
-        unsafe {KING_TABLE[square] = mask_king_attacks(square);}
+// Pext falls back to magics when the build does not have it
+pub fn generate_bishop_attacks_with(backend: SliderBackend, square: Square, board: BitBoard) -> BitBoard {
+    match backend {
+        #[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+        SliderBackend::Pext => generate_pext_moves(square, board, &BISHOP_ATTACKS, &BISHOP_PEXT_TABLE, &BISHOP_PEXT_OFFSETS),
+        _ => generate_slider_moves(square, board, &BISHOP_ATTACKS, &BISHOP_MAGICS, &BISHOP_TABLE, &BISHOP_OFFSETS, &BISHOP_MOVES_COUNTS),
     }
 }
 
-fn mask_king_attacks(square: Square) -> BitBoard {
-    let mut attack = BitBoard::new();
-    if !RANK8.is_square_set(square)            {attack.set_bit(square + NORTH);}
-    if !(RANK8 | H_FILE).is_square_set(square) {attack.set_bit(square + NORTH_EAST);}
-    if !H_FILE.is_square_set(square)           {attack.set_bit(square + EAST);}
-    if !(RANK1 | H_FILE).is_square_set(square) {attack.set_bit(square + SOUTH_EAST);}
-    if !RANK1.is_square_set(square)            {attack.set_bit(square + SOUTH);}
-    if !(RANK1 | A_FILE).is_square_set(square) {attack.set_bit(square + SOUTH_WEST);}
-    if !A_FILE.is_square_set(square)           {attack.set_bit(square + WEST);}
-    if !(RANK8 | A_FILE).is_square_set(square) {attack.set_bit(square + NORTH_WEST);}
-    
-    attack
-}
-
-#[inline(always)]
-pub fn generate_king_attacks(square: Square) -> BitBoard{
-    unsafe {KING_TABLE[square]}
-}
-
-fn initialize_knight_table() {
-    for square in Square::create_squares(0, 64) {
-        unsafe {KNIGHT_TABLE[square] = mask_knight_attacks(square);}
+pub fn generate_rook_attacks_with(backend: SliderBackend, square: Square, board: BitBoard) -> BitBoard {
+    match backend {
+        #[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+        SliderBackend::Pext => generate_pext_moves(square, board, &ROOK_ATTACKS, &ROOK_PEXT_TABLE, &ROOK_PEXT_OFFSETS),
+        _ => generate_slider_moves(square, board, &ROOK_ATTACKS, &ROOK_MAGICS, &ROOK_TABLE, &ROOK_OFFSETS, &ROOK_MOVES_COUNTS),
     }
 }
 
-fn mask_knight_attacks(square: Square) -> BitBoard {
-    let mut attack = BitBoard::new();
+pub fn mask_bishop_attacks(square: Square) -> BitBoard { BitBoard(slider_mask(square.0 as usize, &BISHOP_DIRECTIONS, &RAYS)) }
 
-    if !(TOP_2_RANK    | A_FILE).is_square_set(square) {attack.set_bit(square + NORTH * 2 + WEST);}
-    if !(TOP_2_RANK    | H_FILE).is_square_set(square) {attack.set_bit(square + NORTH * 2 + EAST);}
-    if !(RIGHT_2_FILE  | RANK8 ).is_square_set(square) {attack.set_bit(square + EAST * 2 + NORTH);}
-    if !(RIGHT_2_FILE  | RANK1 ).is_square_set(square) {attack.set_bit(square + EAST * 2 + SOUTH);}
-    if !(BOTTOM_2_RANK | H_FILE).is_square_set(square) {attack.set_bit(square + SOUTH * 2 + EAST);}
-    if !(BOTTOM_2_RANK | A_FILE).is_square_set(square) {attack.set_bit(square + SOUTH * 2 + WEST);}
-    if !(LEFT_2_FILE   | RANK1 ).is_square_set(square) {attack.set_bit(square + WEST * 2 + SOUTH);}
-    if !(LEFT_2_FILE   | RANK8 ).is_square_set(square) {attack.set_bit(square + WEST * 2 + NORTH);}
-
-    attack
+pub fn bishop_attacks_on_fly(square: Square, blocker: BitBoard) -> BitBoard {
+    BitBoard(slider_attacks(square.0 as usize, blocker.0, &BISHOP_DIRECTIONS, &RAYS))
 }
 
-pub fn generate_knight_attacks(square: Square) -> BitBoard {
-    unsafe {KNIGHT_TABLE[square]}
-}
-
-fn initialize_pawn_table() {
-    for square in Square::create_squares(0, 64) {
-        unsafe {
-            PAWN_TABLE[Color::White as usize][square] = mask_pawn_attacks(Color::White, square);
-            PAWN_TABLE[Color::Black as usize][square] = mask_pawn_attacks(Color::Black, square);
-        }
-    }
+#[inline(always)]
+pub fn generate_bishop_attacks(square: Square, board: BitBoard) -> BitBoard {
+    #[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+    { generate_pext_moves(square, board, &BISHOP_ATTACKS, &BISHOP_PEXT_TABLE, &BISHOP_PEXT_OFFSETS) }
+    #[cfg(not(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2")))]
+    { generate_slider_moves(square, board, &BISHOP_ATTACKS, &BISHOP_MAGICS, &BISHOP_TABLE, &BISHOP_OFFSETS, &BISHOP_MOVES_COUNTS) }
 }
 
-fn mask_pawn_attacks(side: Color, square: Square) -> BitBoard {
-    let mut attack = BitBoard::new();
-    
-    match side {
-        Color::White => {
-            if RANK8.is_square_set(square)   {return attack;}
-            if !A_FILE.is_square_set(square) {attack.set_bit(square + NORTH_WEST);}
-            if !H_FILE.is_square_set(square) {attack.set_bit(square + NORTH_EAST);}
-        }
-        Color::Black => {
-            if RANK1.is_square_set(square)   {return attack;}
-            if !A_FILE.is_square_set(square) {attack.set_bit(square + SOUTH_WEST);}
-            if !H_FILE.is_square_set(square) {attack.set_bit(square + SOUTH_EAST);}
-        }
-    }
-    return attack;
+#[inline(always)]
+pub fn generate_king_attacks(square: Square) -> BitBoard{
+    KING_TABLE[square]
 }
 
-fn mask_rook_attacks(square: Square) -> BitBoard {
-    let rank = square.get_rank();
-    let file = square.get_file();
-    let mut attacks = BitBoard::new();
-
-    for i in 1..7 - file {attacks.set_bit(square + EAST * i);}
-
-    for i in 1..file {attacks.set_bit(square + WEST * i);}
-    
-    for i in 1..7 - rank {attacks.set_bit(square + NORTH * i);}
-
-    for i in 1..rank {attacks.set_bit(square + SOUTH * i);}
-    attacks
+pub fn generate_knight_attacks(square: Square) -> BitBoard {
+    KNIGHT_TABLE[square]
 }
 
-fn rook_attacks_on_fly(square: Square, blockers: BitBoard) -> BitBoard {
-    let rank = square.get_rank();
-    let file = square.get_file();
-    let mut attacks = BitBoard::new();
-    for i in 1..8 - file {
-        attacks.set_bit(square + EAST * i);
-        if blockers.is_square_set(square + EAST * i) {break;}
-    }
-    for i in 1..file + 1{
-        attacks.set_bit(square + WEST * i);
-        if blockers.is_square_set(square + WEST * i) {break;}
-    }
-    
-    for i in 1..8 - rank {
-        attacks.set_bit(square + NORTH * i);
-        if blockers.is_square_set(square + NORTH * i) {break;}
-    }
+pub fn mask_rook_attacks(square: Square) -> BitBoard { BitBoard(slider_mask(square.0 as usize, &ROOK_DIRECTIONS, &RAYS)) }
 
-    for i in 1..rank + 1 {
-        attacks.set_bit(square + SOUTH * i);
-        if blockers.is_square_set(square + SOUTH * i) {break;}
-    }
-    attacks
+pub fn rook_attacks_on_fly(square: Square, blockers: BitBoard) -> BitBoard {
+    BitBoard(slider_attacks(square.0 as usize, blockers.0, &ROOK_DIRECTIONS, &RAYS))
 }
 
-
-
 #[inline(always)]
 pub fn generate_rook_attakcs(square: Square, board: BitBoard) -> BitBoard{
-    unsafe {generate_slider_moves(square, board, &ROOK_ATTACKS, &ROOK_MAGICS, &ROOK_TABLE, &ROOK_MOVES_COUNTS)}
-}
-
-fn initialize_rook_components() {
-    unsafe {initialize_slider_table(&mut ROOK_TABLE, &ROOK_MAGICS, mask_rook_attacks, rook_attacks_on_fly);}
-    unsafe {initialize_slider_attacks(mask_rook_attacks, &mut ROOK_ATTACKS)}
+    #[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+    { generate_pext_moves(square, board, &ROOK_ATTACKS, &ROOK_PEXT_TABLE, &ROOK_PEXT_OFFSETS) }
+    #[cfg(not(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2")))]
+    { generate_slider_moves(square, board, &ROOK_ATTACKS, &ROOK_MAGICS, &ROOK_TABLE, &ROOK_OFFSETS, &ROOK_MOVES_COUNTS) }
 }
 
-
 #[inline(always)]
 pub fn genereate_pawn_attacks(square: Square, side: Color) -> BitBoard {
-    unsafe {PAWN_TABLE[side as usize][square]}
+    PAWN_TABLE[side as usize][square]
 }
 
 pub fn generate_queen_attacks(square: Square, board: BitBoard) -> BitBoard {
     generate_rook_attakcs(square, board) | generate_bishop_attacks(square, board)
-}
\ No newline at end of file
+}
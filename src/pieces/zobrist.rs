@@ -0,0 +1,51 @@
+// Zobrist keys, generated at compile time with splitmix64 so every build hashes positions the same way.
+pub struct ZobristKeys {
+    pub pieces:    [[u64; 64]; 12],
+    pub castles:   [u64; 16],
+    pub enpassant: [u64; 8],
+    pub side:      u64,
+}
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+impl ZobristKeys {
+    const fn new() -> Self {
+        let mut keys = Self { pieces: [[0; 64]; 12], castles: [0; 16], enpassant: [0; 8], side: 0 };
+        let mut state = 0x5045_5253_4121_u64;
+        let mut piece = 0;
+        while piece < 12 {
+            let mut square = 0;
+            while square < 64 {
+                let (next, key) = splitmix64(state);
+                state = next;
+                keys.pieces[piece][square] = key;
+                square += 1;
+            }
+            piece += 1;
+        }
+        let mut i = 0;
+        while i < 16 {
+            let (next, key) = splitmix64(state);
+            state = next;
+            keys.castles[i] = key;
+            i += 1;
+        }
+        i = 0;
+        while i < 8 {
+            let (next, key) = splitmix64(state);
+            state = next;
+            keys.enpassant[i] = key;
+            i += 1;
+        }
+        keys.side = splitmix64(state).1;
+        keys
+    }
+}
+
+pub static ZOBRIST_KEYS: ZobristKeys = ZobristKeys::new();
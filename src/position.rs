@@ -0,0 +1,251 @@
+use crate::board_components::{Color, Square};
+use crate::constants::board_constants::START_POS;
+use crate::debug::FenString;
+use crate::pieces::pieces_controller::{BoardSlots, BoardStatus, CastleSlots, MoveBitField, MoveList};
+use crate::san::{move_to_san, san_to_move};
+use std::str::FromStr;
+
+// Typed library API. Positions are plain values and moves are listed from a stack MoveList without any allocation.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    InvalidFen(String),
+    InvalidSquare(String),
+    InvalidFile(String),
+    InvalidRank(String),
+    InvalidColor(String),
+    InvalidPiece(String),
+    IllegalMove(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidFen(err)     => write!(f, "invalid fen: {}", err),
+            Error::InvalidSquare(name) => write!(f, "invalid square {}", name),
+            Error::InvalidFile(name)   => write!(f, "invalid file {}", name),
+            Error::InvalidRank(name)   => write!(f, "invalid rank {}", name),
+            Error::InvalidColor(name)  => write!(f, "invalid color {}", name),
+            Error::InvalidPiece(name)  => write!(f, "invalid piece {}", name),
+            Error::IllegalMove(name)   => write!(f, "illegal move {}", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl PieceKind {
+    pub const ALL: [PieceKind; 6] = [PieceKind::Pawn, PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen, PieceKind::King];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Piece {
+    pub kind:  PieceKind,
+    pub color: Color,
+}
+
+impl Piece {
+    pub fn new(kind: PieceKind, color: Color) -> Self { Self { kind, color } }
+
+    // only the twelve piece slots map to a piece
+    pub fn from_slot(slot: BoardSlots) -> Self {
+        let index = slot as usize;
+        assert!(index < 12, "{:?} is not a piece", slot);
+        Self { kind: PieceKind::ALL[index % 6], color: if index < 6 { Color::White } else { Color::Black } }
+    }
+
+    pub fn get_slot(&self) -> BoardSlots {
+        BoardSlots::iterate_pieces().nth(self.kind as usize + self.color as usize * 6).unwrap()
+    }
+}
+
+impl FromStr for Piece {
+    type Err = Error;
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let mut chars = name.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else { return Err(Error::InvalidPiece(name.to_string())) };
+        let kind = match c.to_ascii_lowercase() {
+            'p' => PieceKind::Pawn,
+            'n' => PieceKind::Knight,
+            'b' => PieceKind::Bishop,
+            'r' => PieceKind::Rook,
+            'q' => PieceKind::Queen,
+            'k' => PieceKind::King,
+            _   => return Err(Error::InvalidPiece(name.to_string())),
+        };
+        Ok(Self::new(kind, if c.is_ascii_uppercase() { Color::White } else { Color::Black }))
+    }
+}
+
+impl std::fmt::Display for Piece {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let letter = ['p', 'n', 'b', 'r', 'q', 'k'][self.kind as usize];
+        write!(f, "{}", if self.color == Color::White { letter.to_ascii_uppercase() } else { letter })
+    }
+}
+
+// a legal move of the position it came from, written as UCI with castling as the king's two square step
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Move(MoveBitField);
+
+impl Move {
+    pub fn get_source(&self) -> Square { self.0.get_source() }
+    // for castling this is the square of the rook the king takes
+    pub fn get_target(&self) -> Square { self.0.get_target() }
+    pub fn get_piece(&self) -> Piece { Piece::from_slot(self.0.get_piece()) }
+    pub fn get_promotion(&self) -> Option<PieceKind> {
+        MoveBitField::is_move_promoted(self.0.get_promoted()).then(|| Piece::from_slot(self.0.get_promoted()).kind)
+    }
+    pub fn is_capture(&self) -> bool { self.0.is_move_capture() }
+    pub fn is_enpassant(&self) -> bool { self.0.is_move_enpassant() }
+    pub fn is_castling(&self) -> bool { self.0.is_move_castling() }
+    pub fn get_uci_name(&self, chess960: bool) -> String { self.0.get_uci_name(chess960) }
+    pub fn get_bitfield(&self) -> MoveBitField { self.0 }
+}
+
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.get_move_name())
+    }
+}
+
+impl std::fmt::Debug for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Move({})", self.0.get_move_name())
+    }
+}
+
+// pseudo legal moves are made and unmade on the iterator's own board, only legal ones come out
+pub struct LegalMoves {
+    board: BoardStatus,
+    moves: MoveList,
+    index: usize,
+}
+
+impl Iterator for LegalMoves {
+    type Item = Move;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.moves.count {
+            let mov = self.moves[self.index];
+            self.index += 1;
+            if let Some(undo) = self.board.make_move(mov) {
+                self.board.unmake_move(mov, &undo);
+                return Some(Move(mov));
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Position {
+    board: BoardStatus,
+}
+
+impl Position {
+    pub fn startpos() -> Self { Self::from_fen(START_POS).unwrap() }
+
+    // X-FEN and Shredder-FEN castling are read as well, the move counters may be left out
+    pub fn from_fen(fen: &str) -> Result<Self, Error> {
+        let board = FenString::parse(fen).map_err(Error::InvalidFen)?.convert_to_board();
+        let mut other_side = board;
+        other_side.change_color();
+        if other_side.is_in_check() { return Err(Error::InvalidFen(format!("the side not to move is in check: {}", fen))); }
+        Ok(Self { board })
+    }
+
+    pub fn get_fen(&self) -> String { FenString::from_board(&self.board).get_fen_string() }
+
+    pub fn get_board(&self) -> &BoardStatus { &self.board }
+    pub fn get_color(&self) -> Color { self.board.get_color() }
+    pub fn get_hash(&self) -> u64 { self.board.get_hash() }
+    pub fn get_half_move(&self) -> usize { self.board.get_half_move() }
+    pub fn get_full_move(&self) -> usize { self.board.get_full_move() }
+    pub fn get_enpassant(&self) -> Option<Square> {
+        let square = self.board.get_enpassant();
+        (square.0 < 64).then_some(square)
+    }
+    pub fn piece_on(&self, square: Square) -> Option<Piece> { self.board.piece_on(square).map(Piece::from_slot) }
+    pub fn is_in_check(&self) -> bool { self.board.is_in_check() }
+
+    pub fn legal_moves(&self) -> LegalMoves {
+        LegalMoves { board: self.board, moves: MoveList::generate(&self.board), index: 0 }
+    }
+
+    pub fn is_legal(&self, mov: Move) -> bool { self.legal_moves().any(|legal| legal == mov) }
+
+    pub fn make_move(&mut self, mov: Move) -> Result<(), Error> {
+        if !self.is_legal(mov) { return Err(Error::IllegalMove(mov.to_string())); }
+        self.board.make_move(mov.0);
+        Ok(())
+    }
+
+    // the position after the move, self stays as it is
+    pub fn play(&self, mov: Move) -> Result<Position, Error> {
+        let mut position = *self;
+        position.make_move(mov)?;
+        Ok(position)
+    }
+
+    // UCI (e2e4, e1g1, e1h1) or SAN (e4, O-O, exd8=Q+)
+    pub fn parse_move(&self, name: &str) -> Result<Move, Error> {
+        if let Some(mov) = self.legal_moves().find(|mov| mov.get_uci_name(false) == name || mov.get_uci_name(true) == name) {
+            return Ok(mov);
+        }
+        san_to_move(&self.board, name).map(Move).map_err(|_| Error::IllegalMove(name.to_string()))
+    }
+
+    pub fn get_san(&self, mov: Move) -> Result<String, Error> {
+        if !self.is_legal(mov) { return Err(Error::IllegalMove(mov.to_string())); }
+        Ok(move_to_san(&self.board, mov.0))
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self { Self::startpos() }
+}
+
+impl FromStr for Position {
+    type Err = Error;
+    fn from_str(fen: &str) -> Result<Self, Self::Err> { Self::from_fen(fen) }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get_fen())
+    }
+}
+
+impl std::fmt::Debug for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Position({})", self.get_fen())
+    }
+}
+
+// the same fields the fen holds, castling rook squares only count while the right is there
+impl PartialEq for Position {
+    fn eq(&self, other: &Self) -> bool {
+        let (board, other) = (&self.board, &other.board);
+        BoardSlots::iterate_board_slots(BoardSlots::WhitePawn, BoardSlots::BlackKing).all(|slot| board[slot] == other[slot]) &&
+            board.get_color() == other.get_color() && board.get_enpassant() == other.get_enpassant() &&
+            board.get_half_move() == other.get_half_move() && board.get_full_move() == other.get_full_move() &&
+            CastleSlots::ALL.iter().all(|castle| board.can_castle(*castle) == other.can_castle(*castle) &&
+                (!board.can_castle(*castle) || board.get_castle_rook(*castle) == other.get_castle_rook(*castle)))
+    }
+}
+
+impl Eq for Position {}
+
+impl From<BoardStatus> for Position {
+    fn from(board: BoardStatus) -> Self { Self { board } }
+}
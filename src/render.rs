@@ -0,0 +1,171 @@
+use crate::board_components::{Color, Square};
+use crate::constants::board_constants::{ASCII_PIECES, UNICODE_PIECES};
+use crate::pieces::pieces_controller::{BoardSlots, BoardStatus};
+
+// Text boards for terminals and standalone SVG images for reports. Every style can be drawn from either side.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderStyle {
+    Ascii,
+    Unicode,
+    // coloured squares and pieces with ANSI escape codes
+    Ansi,
+}
+
+const SVG_SQUARE: usize = 45;
+const SVG_MARGIN: usize = 20;
+const SVG_LIGHT: &str = "#f0d9b5";
+const SVG_DARK: &str = "#b58863";
+const SVG_HIGHLIGHT: &str = "#cdd26a";
+const SVG_ARROW: &str = "#15781b";
+
+// solid glyphs for both sides, the colour tells them apart
+const FILLED_PIECES: [char; 6] = ['♟', '♞', '♝', '♜', '♛', '♚'];
+
+const ANSI_LIGHT: &str = "\x1b[48;5;180m";
+const ANSI_DARK: &str = "\x1b[48;5;137m";
+const ANSI_HIGHLIGHT: &str = "\x1b[48;5;143m";
+const ANSI_WHITE_PIECE: &str = "\x1b[1;97m";
+const ANSI_BLACK_PIECE: &str = "\x1b[1;30m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+pub struct BoardRenderer {
+    pub style:       RenderStyle,
+    // the side at the bottom of the board
+    pub perspective: Color,
+    pub coordinates: bool,
+    pub last_move:   Option<(Square, Square)>,
+    pub arrows:      Vec<(Square, Square)>,
+}
+
+impl BoardRenderer {
+    pub fn new() -> Self {
+        Self { style: RenderStyle::Ascii, perspective: Color::White, coordinates: true, last_move: None, arrows: Vec::new() }
+    }
+
+    pub fn set_style(mut self, style: RenderStyle) -> Self {
+        self.style = style;
+        self
+    }
+    pub fn set_perspective(mut self, perspective: Color) -> Self {
+        self.perspective = perspective;
+        self
+    }
+    pub fn set_coordinates(mut self, coordinates: bool) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+    pub fn set_last_move(mut self, source: Square, target: Square) -> Self {
+        self.last_move = Some((source, target));
+        self
+    }
+    pub fn add_arrow(mut self, source: Square, target: Square) -> Self {
+        self.arrows.push((source, target));
+        self
+    }
+
+    // ranks and files in the order they are drawn, top to bottom and left to right
+    fn get_ranks(&self) -> Vec<u8> {
+        if self.perspective == Color::White { (0..8).rev().collect() } else { (0..8).collect() }
+    }
+    fn get_files(&self) -> Vec<u8> {
+        if self.perspective == Color::White { (0..8).collect() } else { (0..8).rev().collect() }
+    }
+
+    fn is_highlighted(&self, square: Square) -> bool {
+        self.last_move.is_some_and(|(source, target)| square == source || square == target)
+    }
+
+    fn get_piece_text(&self, piece: Option<BoardSlots>) -> String {
+        match (self.style, piece) {
+            (RenderStyle::Ascii, Some(piece))   => ASCII_PIECES[piece as usize].to_string(),
+            (RenderStyle::Unicode, Some(piece)) => UNICODE_PIECES[piece as usize].to_string(),
+            (RenderStyle::Ansi, Some(piece))    => {
+                let color = if (piece as usize) < 6 { ANSI_WHITE_PIECE } else { ANSI_BLACK_PIECE };
+                format!("{}{}", color, FILLED_PIECES[piece as usize % 6])
+            },
+            (RenderStyle::Ansi, None) => " ".to_string(),
+            (_, None) => ".".to_string(),
+        }
+    }
+
+    pub fn render_text(&self, board: &BoardStatus) -> String {
+        let files = self.get_files();
+        let mut result = String::new();
+        if self.style != RenderStyle::Ansi { result += "  +-----------------+\n"; }
+        for rank in self.get_ranks() {
+            result += &if self.coordinates { format!("{} ", rank + 1) } else { "  ".to_string() };
+            if self.style != RenderStyle::Ansi { result += "|"; }
+            for file in &files {
+                let square = Square(rank * 8 + file);
+                let piece = self.get_piece_text(board.piece_on(square));
+                if self.style == RenderStyle::Ansi {
+                    let background = if self.is_highlighted(square) { ANSI_HIGHLIGHT } else if (rank + file) % 2 == 1 { ANSI_LIGHT } else { ANSI_DARK };
+                    result += &format!("{} {} {}", background, piece, ANSI_RESET);
+                }
+                else { result += &format!(" {}", piece); }
+            }
+            if self.style != RenderStyle::Ansi { result += " |"; }
+            result += "\n";
+        }
+        if self.style != RenderStyle::Ansi { result += "  +-----------------+\n"; }
+        if self.coordinates {
+            // under the pieces, squares are three characters wide in colour and two in text
+            let width = if self.style == RenderStyle::Ansi { 3 } else { 2 };
+            result += if self.style == RenderStyle::Ansi { "  " } else { "    " };
+            result += files.iter().map(|file| format!("{:^width$}", (b'a' + file) as char, width = width)).collect::<String>().trim_end();
+            result += "\n";
+        }
+        result
+    }
+
+    // top left corner of a square in the image
+    fn get_svg_position(&self, square: Square) -> (usize, usize) {
+        let column = self.get_files().iter().position(|file| *file == square.get_file()).unwrap();
+        let row = self.get_ranks().iter().position(|rank| *rank == square.get_rank()).unwrap();
+        (SVG_MARGIN + column * SVG_SQUARE, SVG_MARGIN + row * SVG_SQUARE)
+    }
+
+    pub fn render_svg(&self, board: &BoardStatus) -> String {
+        let size = SVG_SQUARE * 8 + SVG_MARGIN * 2;
+        let half = SVG_SQUARE / 2;
+        let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">\n", size);
+        svg += &format!("<defs><marker id=\"arrowhead\" markerWidth=\"4\" markerHeight=\"4\" refX=\"2\" refY=\"2\" orient=\"auto\"><path d=\"M0,0 L4,2 L0,4 z\" fill=\"{}\"/></marker></defs>\n", SVG_ARROW);
+        svg += &format!("<rect x=\"0\" y=\"0\" width=\"{0}\" height=\"{0}\" fill=\"#262421\"/>\n", size);
+        for square in Square::create_squares(0, 64) {
+            let (x, y) = self.get_svg_position(square);
+            let color = if (square.get_rank() + square.get_file()) % 2 == 1 { SVG_LIGHT } else { SVG_DARK };
+            svg += &format!("<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"{3}\"/>\n", x, y, SVG_SQUARE, color);
+            if self.is_highlighted(square) {
+                svg += &format!("<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"{3}\" fill-opacity=\"0.8\"/>\n", x, y, SVG_SQUARE, SVG_HIGHLIGHT);
+            }
+        }
+        if self.coordinates {
+            for (index, (file, rank)) in self.get_files().iter().zip(self.get_ranks()).enumerate() {
+                let center = SVG_MARGIN + index * SVG_SQUARE + half;
+                svg += &format!("<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"14\" fill=\"#e0e0e0\" text-anchor=\"middle\">{}</text>\n",
+                    center, size - 5, (b'a' + file) as char);
+                svg += &format!("<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"14\" fill=\"#e0e0e0\" text-anchor=\"middle\">{}</text>\n",
+                    SVG_MARGIN / 2, center + 5, rank + 1);
+            }
+        }
+        for square in Square::create_squares(0, 64) {
+            let Some(piece) = board.piece_on(square) else { continue };
+            let (x, y) = self.get_svg_position(square);
+            let (fill, stroke) = if (piece as usize) < 6 { ("#ffffff", "#000000") } else { ("#000000", "#ffffff") };
+            svg += &format!("<text x=\"{}\" y=\"{}\" font-size=\"38\" text-anchor=\"middle\" fill=\"{}\" stroke=\"{}\" stroke-width=\"1\">{}</text>\n",
+                x + half, y + SVG_SQUARE - 8, fill, stroke, FILLED_PIECES[piece as usize % 6]);
+        }
+        for (source, target) in &self.arrows {
+            let ((x1, y1), (x2, y2)) = (self.get_svg_position(*source), self.get_svg_position(*target));
+            svg += &format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"8\" stroke-opacity=\"0.8\" stroke-linecap=\"round\" marker-end=\"url(#arrowhead)\"/>\n",
+                x1 + half, y1 + half, x2 + half, y2 + half, SVG_ARROW);
+        }
+        svg += "</svg>\n";
+        svg
+    }
+}
+
+impl Default for BoardRenderer {
+    fn default() -> Self { Self::new() }
+}
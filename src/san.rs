@@ -0,0 +1,65 @@
+use crate::constants::board_constants::ASCII_PIECES;
+use crate::pieces::pieces_controller::{BoardSlots, BoardStatus, MoveBitField, MoveList};
+
+pub fn legal_moves(board: &BoardStatus) -> Vec<MoveBitField> {
+    MoveList::generate(board).iterate_moves().filter(|mov| {
+        let mut copy = *board;
+        copy.make_move(*mov).is_some()
+    }).collect()
+}
+
+fn piece_letter(piece: BoardSlots) -> &'static str { ASCII_PIECES[piece as usize % 6] }
+
+// standard algebraic notation, the move has to be legal on the board
+pub fn move_to_san(board: &BoardStatus, mov: MoveBitField) -> String {
+    let mut san = if mov.is_move_castling() {
+        if board.get_castle_of_move(mov).is_king_side() { "O-O".to_string() } else { "O-O-O".to_string() }
+    }
+    else {
+        let (source, target) = (mov.get_source(), mov.get_target());
+        let mut san = String::new();
+        if piece_letter(mov.get_piece()) == "P" {
+            if mov.is_move_capture() { san.push((b'a' + source.get_file()) as char); }
+        }
+        else {
+            san += piece_letter(mov.get_piece());
+            // other pieces of the same kind that can reach the target decide how much of the source is written
+            let rivals: Vec<_> = legal_moves(board).into_iter()
+                .filter(|other| other.get_piece() == mov.get_piece() && other.get_target() == target && other.get_source() != source && !other.is_move_castling())
+                .map(|other| other.get_source()).collect();
+            if !rivals.is_empty() {
+                let file = (b'a' + source.get_file()) as char;
+                let rank = (b'1' + source.get_rank()) as char;
+                if rivals.iter().all(|rival| rival.get_file() != source.get_file()) { san.push(file); }
+                else if rivals.iter().all(|rival| rival.get_rank() != source.get_rank()) { san.push(rank); }
+                else { san.push(file); san.push(rank); }
+            }
+        }
+        if mov.is_move_capture() { san.push('x'); }
+        san += &target.get_uci_name();
+        if MoveBitField::is_move_promoted(mov.get_promoted()) {
+            san.push('=');
+            san += piece_letter(mov.get_promoted());
+        }
+        san
+    };
+    let mut after = *board;
+    if after.make_move(mov).is_some() && after.is_in_check() {
+        san.push(if legal_moves(&after).is_empty() { '#' } else { '+' });
+    }
+    san
+}
+
+// check marks, annotations, '=' and 'x' are optional, 0-0 is read as O-O and a UCI move name is accepted as well
+fn normalize_san(san: &str) -> String {
+    san.replace("e.p.", "").replace('0', "O").chars().filter(|c| !"+#!?=x".contains(*c)).collect()
+}
+
+pub fn san_to_move(board: &BoardStatus, san: &str) -> Result<MoveBitField, String> {
+    let name = normalize_san(san);
+    let moves = legal_moves(board);
+    moves.iter().find(|mov| normalize_san(&move_to_san(board, **mov)) == name)
+        .or_else(|| moves.iter().find(|mov| mov.get_move_name() == san))
+        .copied()
+        .ok_or(format!("illegal move {}", san))
+}
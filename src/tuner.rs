@@ -0,0 +1,158 @@
+use crate::board_components::Color;
+use crate::debug::FenString;
+use crate::eveluation::eveluate;
+use crate::eveluation_params::EvalParams;
+use crate::pieces::pieces_controller::{BoardStatus, MoveBitField, MoveList};
+use crate::uci::UciInformation;
+use std::fs;
+
+// Texel tuning: positions are labelled with the game result from white's point of view
+// (1.0 win, 0.5 draw, 0.0 loss) and the weights are changed until
+// sigmoid(K * eval) predicts those results with the smallest mean squared error.
+
+pub struct TuningEntry {
+    // quiet position reached by the quiescence search, this is what gets evaluated
+    pub board:  BoardStatus,
+    pub result: f64,
+}
+
+// accepts EPD lines like `<fen> c9 "1-0";`, `<fen> [0.5]` and csv lines like `<fen>,1.0`
+pub fn parse_entry(line: &str) -> Option<(String, f64)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') { return None; }
+
+    let label = if let Some(start) = line.find('"') { line[start + 1..].split('"').next()? }
+        else if let Some(start) = line.find('[') { line[start + 1..].split(']').next()? }
+        else if let Some((_, label)) = line.rsplit_once(',') { label }
+        else { line.split_whitespace().last()? };
+    let label = label.trim();
+    let result = match label {
+        "1-0"     => 1.0,
+        "0-1"     => 0.0,
+        "1/2-1/2" => 0.5,
+        _         => label.parse().ok()?,
+    };
+    if !(0.0..=1.0).contains(&result) { return None; }
+
+    // move counters do not change the evaluation, EPD lines usually do not even have them
+    let position = line.split([',', '[', '"']).next()?;
+    let fields: Vec<&str> = position.split_whitespace().take(4).collect();
+    if fields.len() < 4 { return None; }
+    Some((format!("{} 0 1", fields.join(" ")), result))
+}
+
+pub fn load_entries(path: &str, params: &EvalParams) -> Result<Vec<TuningEntry>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("can not read {}: {}", path, err))?;
+    let mut uci_info = UciInformation::new().set_eval_params(*params);
+    let mut entries = Vec::new();
+    for line in text.lines() {
+        let Some((fen, result)) = parse_entry(line) else { continue };
+        uci_info.board = FenString::new(fen).convert_to_board();
+        let (_, board) = resolve_quiet(&mut uci_info, 1000000, -1000000);
+        entries.push(TuningEntry { board, result });
+    }
+    Ok(entries)
+}
+
+// same search as eveluation::quiescence but it also returns the position the score came from
+pub fn resolve_quiet(uci_info: &mut UciInformation, beta: isize, mut alpha: isize) -> (isize, BoardStatus) {
+    let stand_pat = eveluate(&uci_info.board, &uci_info.eval_params);
+    let mut leaf = uci_info.board;
+    if stand_pat >= beta { return (beta, leaf); }
+    alpha = isize::max(alpha, stand_pat);
+
+    for mov in MoveList::new(uci_info).iterate_moves().filter(MoveBitField::is_move_capture) {
+        if let Some(undo) = uci_info.board.make_move(mov) {
+            let (score, child_leaf) = resolve_quiet(uci_info, -alpha, -beta);
+            uci_info.board.unmake_move(mov, &undo);
+            if -score >= beta { return (beta, child_leaf); }
+            if -score > alpha {
+                alpha = -score;
+                leaf = child_leaf;
+            }
+        }
+    }
+    (alpha, leaf)
+}
+
+#[inline(always)]
+fn sigmoid(k: f64, score: isize) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score as f64 / 400.0))
+}
+
+pub struct Tuner {
+    pub entries: Vec<TuningEntry>,
+    pub params:  EvalParams,
+    pub k:       f64,
+    pub threads: usize,
+}
+
+impl Tuner {
+    pub fn new(entries: Vec<TuningEntry>, params: EvalParams, threads: usize) -> Self {
+        Self { entries, params, k: 1.0, threads: usize::max(threads, 1) }
+    }
+
+    pub fn error(&self, params: &EvalParams, k: f64) -> f64 {
+        if self.entries.is_empty() { return 0.0; }
+        let chunk_size = self.entries.len().div_ceil(self.threads);
+        let total: f64 = std::thread::scope(|scope| {
+            let handles: Vec<_> = self.entries.chunks(chunk_size).map(|chunk| scope.spawn(move || {
+                chunk.iter().map(|entry| {
+                    let score = eveluate(&entry.board, params);
+                    let white_score = match entry.board.get_color() {
+                        Color::White => score,
+                        Color::Black => -score,
+                    };
+                    (entry.result - sigmoid(k, white_score)).powi(2)
+                }).sum::<f64>()
+            })).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).sum()
+        });
+        total / self.entries.len() as f64
+    }
+
+    // golden section search, the error is unimodal in K
+    pub fn fit_k(&mut self) -> f64 {
+        let ratio = (5f64.sqrt() - 1.0) / 2.0;
+        let (mut low, mut high) = (0.0, 10.0);
+        for _ in 0..60 {
+            let left  = high - ratio * (high - low);
+            let right = low + ratio * (high - low);
+            if self.error(&self.params, left) < self.error(&self.params, right) { high = right; }
+            else { low = left; }
+        }
+        self.k = (low + high) / 2.0;
+        self.k
+    }
+
+    // coordinate descent: every weight is moved by +step and -step and the change is kept when
+    // the error goes down, the params file is rewritten after each pass so a run can be stopped any time
+    pub fn tune(&mut self, max_iterations: usize, step: isize, output_path: &str) -> Result<f64, String> {
+        let mut values = self.params.get_values();
+        let mut best_error = self.error(&self.params, self.k);
+        println!("initial error: {:.8} K: {:.4}", best_error, self.k);
+
+        for iteration in 1..=max_iterations {
+            let mut improved = false;
+            for index in 0..values.len() {
+                for delta in [step, -step] {
+                    values[index] += delta;
+                    let mut candidate = self.params;
+                    candidate.set_values(&values);
+                    let error = self.error(&candidate, self.k);
+                    if error < best_error {
+                        best_error = error;
+                        self.params = candidate;
+                        improved = true;
+                        break;
+                    }
+                    values[index] -= delta;
+                }
+            }
+            self.params.save(output_path)?;
+            println!("iteration {}: error {:.8}", iteration, best_error);
+            if !improved { break; }
+        }
+        Ok(best_error)
+    }
+}
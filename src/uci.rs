@@ -1,13 +1,20 @@
-use std::cmp::Ordering;
 use std::str::SplitWhitespace;
 use std::time::{Instant, Duration};
+use crate::board_components::Color;
 use crate::pieces::pieces_controller::{BoardStatus, MoveBitField, MoveList, BoardHistory};
-use crate::debug::FenString;
+use crate::debug::{display_position, FenString};
 use std::sync::{Arc, RwLock};
-use crate::debug::perft_driver;
-use crate::eveluation::find_best_move;
-
-const START_POS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+use crate::perft::{perft_divide, perft_stats, perft_suite, print_perft, print_perft_stats, PerftTable};
+use crate::eveluation::{find_best_move, trace_eveluate};
+use crate::eveluation_params::EvalParams;
+use crate::nnue::{Network, NnueState};
+use crate::gensfen::{gensfen, GensfenOptions};
+use crate::bench::{bench, bench_sliders, print_bench, print_slider_bench, BENCH_DEPTH, SLIDER_BENCH_ITERATIONS};
+use crate::epd::{print_epd_summary, run_epd, EpdLimits};
+use crate::render::{BoardRenderer, RenderStyle};
+use crate::board_components::Square;
+use crate::xboard::xboard_loop;
+use crate::constants::board_constants::START_POS;
 
 pub struct UciInformation {
     pub find_move_signal  : Arc<RwLock<bool>>,
@@ -31,7 +38,18 @@ pub struct UciInformation {
 
     pub start_time        : Instant,
     pub node_count        : usize,
+    // distance from the root of the current search, killer moves are stored per ply
+    pub ply               : usize,
     pub board_history     : BoardHistory,
+    pub eval_params       : EvalParams,
+    // evaluation goes through the network instead of eveluate when an EvalFile is loaded
+    pub nnue              : Option<NnueState>,
+    // UCI_Chess960, castling moves are read and written as king takes rook
+    pub chess960          : bool,
+    // debug on, position and search details are sent as info strings
+    pub debug             : bool,
+    // called after every completed depth, the xboard front-end prints its thinking output here
+    pub report_iteration  : Option<fn(&UciInformation)>,
 }
 
 impl UciInformation {
@@ -49,6 +67,7 @@ impl UciInformation {
             nodes_limit       : None,
             search_until_mate : false,
             node_count        : 0,
+            ply               : 0,
             ponder_search     : false,
             infinity_search   : false,
             time_limit        : None,
@@ -56,6 +75,11 @@ impl UciInformation {
             board             : BoardStatus::new(),
             board_history     : BoardHistory::new(),
             stop_signal       : Arc::new(RwLock::new(false)),
+            eval_params       : EvalParams::new(),
+            nnue              : None,
+            chess960          : false,
+            debug             : false,
+            report_iteration  : None,
         }
     }
     
@@ -79,7 +103,13 @@ impl UciInformation {
             stop_signal: self.stop_signal.clone(), 
             start_time: self.start_time, 
             node_count: self.node_count, 
+            ply: self.ply,
             board_history: self.board_history, 
+            eval_params: self.eval_params,
+            nnue: self.nnue.clone(),
+            chess960: self.chess960,
+            debug: self.debug,
+            report_iteration: self.report_iteration,
         }
     }
 
@@ -87,7 +117,7 @@ impl UciInformation {
         if *self.stop_signal.read().unwrap() || *self.quit_signal.read().unwrap() {return true;}
         if let Some(time)  = self.time_limit {
             let time_limit = Duration::from_millis(time as u64);
-            if self.start_time.elapsed().cmp(&time_limit) == Ordering::Less {return true;}
+            if self.start_time.elapsed() >= time_limit {return true;}
         }
         if let Some(node_limit) = self.nodes_limit {
             if self.node_count >= node_limit { return true; }
@@ -146,26 +176,54 @@ impl UciInformation {
         self.board = board;
         self
     }
+
+    pub fn set_eval_params(mut self, eval_params: EvalParams) -> Self {
+        self.eval_params = eval_params;
+        self
+    }
+
+    pub fn set_nnue(mut self, nnue: Option<NnueState>) -> Self {
+        self.nnue = nnue;
+        self
+    }
+
+    pub fn set_chess960(mut self, chess960: bool) -> Self {
+        self.chess960 = chess960;
+        self
+    }
+
+    pub fn set_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    pub fn set_report_iteration(mut self, report_iteration: Option<fn(&UciInformation)>) -> Self {
+        self.report_iteration = report_iteration;
+        self
+    }
 }
 
 pub fn get_move(uci_info: &UciInformation, move_name: String) -> MoveBitField {
     let moves = MoveList::new(uci_info);
-    let mov = moves.iterate_moves().find(|mov| mov.get_move_name() == move_name).unwrap();
+    let mov = moves.iterate_moves().find(|mov| mov.get_uci_name(uci_info.chess960) == move_name).unwrap();
     mov
 }
 
-pub fn position(mut data: SplitWhitespace, uci_info: &mut UciInformation) {
-    match data.next() {
-        Some("startpos") => uci_info.board = FenString::new(START_POS.to_string()).convert_to_board(),
-        Some("fen")      => {
-            uci_info.board = FenString::new(data.clone().collect::<Vec<&str>>().join(" ")).convert_to_board();
-            data.next();
-        }
+pub fn position(data: SplitWhitespace, uci_info: &mut UciInformation) {
+    let tokens: Vec<&str> = data.collect();
+    let moves_index = tokens.iter().position(|token| *token == "moves").unwrap_or(tokens.len());
+    match tokens.first() {
+        Some(&"startpos") => uci_info.board = FenString::new(START_POS.to_string()).convert_to_board(),
+        Some(&"fen")      => uci_info.board = FenString::new(tokens[1..moves_index].join(" ")).convert_to_board(),
         _ => println!("unkown arguments"),
     }
 
-    if let Some("moves") = data.next() {
-        for mov in data { uci_info.board.make_move(get_move(&uci_info, mov.to_string())); }
+    for mov in tokens.iter().skip(moves_index + 1) {
+        let mov = get_move(uci_info, mov.to_string());
+        uci_info.board.make_move(mov);
+    }
+    if uci_info.debug {
+        println!("info string position {} key {:016X}", FenString::from_board(&uci_info.board).get_fen_string(), uci_info.board.get_hash());
     }
 }
 
@@ -183,16 +241,192 @@ pub fn go(mut data: SplitWhitespace, uci_info: &mut UciInformation) {
             Some("mate")      => uci_info.moves_to_go     = Some(data.next().unwrap().parse().unwrap()),
             Some("ponder")    => uci_info.ponder_search   = true,
             Some("infinite")  => uci_info.infinity_search = true,
-            Some("perft")     => {
-                perft_driver(&uci_info.copy().set_depth_limit(data.next().unwrap().parse().unwrap()));
-                return;
-            },
+            Some("perft")     => return perft(data, uci_info),
             _ => break,
         }
     }
+    if uci_info.debug {
+        let limits = [("wtime", Some(uci_info.wtime)), ("btime", Some(uci_info.btime)), ("movestogo", uci_info.moves_to_go),
+            ("nodes", uci_info.nodes_limit), ("movetime", uci_info.time_limit)];
+        let limits: Vec<String> = limits.iter().filter_map(|(name, value)| value.filter(|value| *value != usize::MAX).map(|value| format!("{} {}", name, value))).collect();
+        println!("info string go depth {} {}", uci_info.depth_limit, limits.join(" "));
+    }
     *uci_info.find_move_signal.write().unwrap() = true;
 }
 
+// go perft <depth> [threads N] [hash MB], go perft <depth> stats
+pub fn perft(mut data: SplitWhitespace, uci_info: &UciInformation) {
+    let Some(Ok(depth)) = data.next().map(str::parse) else { return println!("info string perft needs a depth") };
+    if data.clone().any(|token| token == "stats") { return print_perft_stats(&perft_stats(&uci_info.board, depth)); }
+    let (threads, hash_mb) = parse_perft_options(data);
+    let table = (hash_mb > 0).then(|| PerftTable::new(hash_mb));
+    print_perft(&perft_divide(&uci_info.board, depth, threads, table.as_ref(), uci_info.chess960));
+}
+
+// perftsuite <max depth> [threads N] [hash MB] [file PATH], the file defaults to perfts.txt
+pub fn run_perft_suite(mut data: SplitWhitespace) {
+    let Some(Ok(max_depth)) = data.next().map(str::parse) else { return println!("info string perftsuite needs a depth") };
+    let tokens: Vec<&str> = data.collect();
+    let path = tokens.iter().position(|token| *token == "file").and_then(|index| tokens.get(index + 1)).unwrap_or(&"perfts.txt");
+    let (threads, hash_mb) = parse_perft_options(tokens.join(" ").split_whitespace());
+    if let Err(err) = perft_suite(path, max_depth, threads, hash_mb) { println!("info string {}", err); }
+}
+
+fn parse_perft_options(mut data: SplitWhitespace) -> (usize, usize) {
+    let (mut threads, mut hash_mb) = (1, 0);
+    while let Some(key) = data.next() {
+        match (key, data.next().map(str::parse)) {
+            ("threads", Some(Ok(value))) => threads = value,
+            ("hash", Some(Ok(value)))    => hash_mb = value,
+            _ => {},
+        }
+    }
+    (threads, hash_mb)
+}
+
+pub fn eval(uci_info: &UciInformation) {
+    println!("{}", uci_info.board);
+    println!("{}", trace_eveluate(&uci_info.board, &uci_info.eval_params));
+    if let Some(nnue) = &uci_info.nnue {
+        let mut nnue = nnue.clone();
+        nnue.reset(&uci_info.board);
+        let score = match uci_info.board.get_color() {
+            Color::White => nnue.eveluate(Color::White),
+            Color::Black => -nnue.eveluate(Color::Black),
+        };
+        println!("NNUE evaluation: {} (white side)", score);
+    }
+}
+
+// setoption name <name> value <value>, besides the listed options every evaluation
+// parameter name from EvalParams is accepted so tuners can change weights directly
+pub fn set_option(data: SplitWhitespace, uci_info: &mut UciInformation) {
+    let tokens: Vec<&str> = data.collect();
+    let value_index = tokens.iter().position(|token| *token == "value").unwrap_or(tokens.len());
+    if tokens.first() != Some(&"name") {
+        println!("info string setoption needs a name");
+        return;
+    }
+    let name  = tokens[1..value_index].join(" ");
+    let value = tokens[usize::min(value_index + 1, tokens.len())..].join(" ");
+    match name.as_str() {
+        "EvalParamsFile" => match EvalParams::load(&value) {
+            Ok(eval_params) => uci_info.eval_params = eval_params,
+            Err(err) => println!("info string {}", err),
+        },
+        "UCI_Chess960" => uci_info.chess960 = value == "true",
+        "EvalFile" if value.is_empty() || value == "<empty>" => uci_info.nnue = None,
+        "EvalFile" => match Network::load(&value) {
+            Ok(network) => uci_info.nnue = Some(NnueState::new(Arc::new(network))),
+            Err(err) => println!("info string {}", err),
+        },
+        _ => match value.parse() {
+            Ok(value) if uci_info.eval_params.set_param(&name, value) => {},
+            _ => println!("info string unknown option {} or invalid value {}", name, value),
+        },
+    }
+}
+
+pub fn save_params(mut data: SplitWhitespace, uci_info: &UciInformation) {
+    match data.next() {
+        Some(path) => if let Err(err) = uci_info.eval_params.save(path) { println!("info string {}", err) },
+        None => println!("info string saveparams needs a file name"),
+    }
+}
+
+pub fn generate_data(data: SplitWhitespace, uci_info: &UciInformation) {
+    let options = match GensfenOptions::parse(data) {
+        Ok(options) => options,
+        Err(err) => return println!("info string {}", err),
+    };
+    match gensfen(&options, uci_info.eval_params, uci_info.nnue.clone()) {
+        Ok(positions) => println!("info string gensfen wrote {} positions to {}", positions, options.output),
+        Err(err) => println!("info string {}", err),
+    }
+}
+
+// bench [depth] [threads] [hash], the search has no transposition table yet so the hash size is accepted and ignored
+pub fn run_bench(mut data: SplitWhitespace, uci_info: &UciInformation) {
+    if data.clone().next() == Some("sliders") {
+        let Ok(iterations) = data.nth(1).map_or(Ok(SLIDER_BENCH_ITERATIONS), str::parse) else {
+            return println!("info string bench sliders [iterations]");
+        };
+        return print_slider_bench(iterations, &bench_sliders(iterations));
+    }
+    let mut number = |default: usize| data.next().map_or(Ok(default), str::parse);
+    let (Ok(depth), Ok(threads), Ok(_hash_mb)) = (number(BENCH_DEPTH as usize), number(1), number(16)) else {
+        return println!("info string bench [depth] [threads] [hash]");
+    };
+    let result = bench(depth as isize, threads, uci_info.eval_params, uci_info.nnue.clone());
+    print_bench(&result);
+}
+
+// epd <file> [file ...] [depth N] [nodes N] [movetime MS] [output FILE]
+pub fn run_epd_suite(mut data: SplitWhitespace, uci_info: &UciInformation) {
+    let (mut paths, mut limits, mut output) = (Vec::new(), EpdLimits::new(), None);
+    while let Some(token) = data.next() {
+        if !["depth", "nodes", "movetime", "output"].contains(&token) { paths.push(token); continue; }
+        let Some(value) = data.next() else { return println!("info string {} needs a value", token) };
+        if token == "output" { output = Some(value); continue; }
+        let Ok(number) = value.parse::<usize>() else { return println!("info string invalid value {} for {}", value, token) };
+        limits = match token {
+            "depth" => limits.set_depth(number as isize),
+            "nodes" => limits.set_nodes(number),
+            _       => limits.set_movetime(number),
+        };
+    }
+    if paths.is_empty() { return println!("info string epd needs a file"); }
+    match run_epd(&paths, &limits, uci_info.eval_params, uci_info.nnue.clone(), output) {
+        Ok(results) => print_epd_summary(&results),
+        Err(err) => println!("info string {}", err),
+    }
+}
+
+// render <ascii|unicode|ansi|svg> [flip] [nocoords] [lastmove e2e4] [arrow g1f3 ...] [output FILE] <startpos | fen>
+pub fn run_render(mut data: SplitWhitespace) {
+    let usage = "info string render <ascii|unicode|ansi|svg> [flip] [nocoords] [lastmove MOVE] [arrow MOVE] [output FILE] <startpos | fen>";
+    let style = match data.next() {
+        Some("ascii")   => Some(RenderStyle::Ascii),
+        Some("unicode") => Some(RenderStyle::Unicode),
+        Some("ansi")    => Some(RenderStyle::Ansi),
+        Some("svg")     => None,
+        _ => return println!("{}", usage),
+    };
+    let squares = |name: &str| -> Option<(Square, Square)> { Some((name.get(0..2)?.parse().ok()?, name.get(2..4)?.parse().ok()?)) };
+    let (mut renderer, mut output, mut fen) = (BoardRenderer::new().set_style(style.unwrap_or(RenderStyle::Ascii)), None, Vec::new());
+    while let Some(token) = data.next() {
+        match token {
+            "flip"     => renderer = renderer.set_perspective(Color::Black),
+            "nocoords" => renderer = renderer.set_coordinates(false),
+            "lastmove" | "arrow" | "output" => {
+                let Some(value) = data.next() else { return println!("info string {} needs a value", token) };
+                if token == "output" { output = Some(value); continue; }
+                let Some((source, target)) = squares(value) else { return println!("info string invalid move {}", value) };
+                renderer = if token == "arrow" { renderer.add_arrow(source, target) } else { renderer.set_last_move(source, target) };
+            },
+            _ => fen.push(token),
+        }
+    }
+    let fen = if fen.is_empty() || fen == ["startpos"] { START_POS.to_string() } else { fen.join(" ") };
+    let board = match FenString::parse(&fen) {
+        Ok(fen) => fen.convert_to_board(),
+        Err(err) => return println!("info string {}", err),
+    };
+    let result = if style.is_some() { renderer.render_text(&board) } else { renderer.render_svg(&board) };
+    match output {
+        Some(path) => if let Err(err) = std::fs::write(path, result) { println!("info string can not write {}: {}", path, err) },
+        None => print!("{}", result),
+    }
+}
+
+// debug on|off
+pub fn set_debug(mut data: SplitWhitespace, uci_info: &mut UciInformation) {
+    match data.next() {
+        Some("on")  => uci_info.debug = true,
+        Some("off") => uci_info.debug = false,
+        _ => println!("info string debug needs on or off"),
+    }
+}
 
 pub fn get_best_move(mut uci_info: UciInformation) {
     uci_info.node_count = 0;
@@ -204,7 +438,10 @@ pub fn get_best_move(mut uci_info: UciInformation) {
     *uci_info.stop_signal.write().unwrap() = false;
     *uci_info.find_move_signal.write().unwrap() = false;
     *uci_info.is_searching.write().unwrap() = false;
-    println!("bestmove {}", uci_info.board_history.found_best_move.get_move_name());
+    if uci_info.debug {
+        println!("info string searched {} nodes in {} ms", uci_info.node_count, uci_info.start_time.elapsed().as_millis());
+    }
+    println!("bestmove {}", uci_info.board_history.found_best_move.get_uci_name(uci_info.chess960));
 }
 
 pub fn uci_loop() {
@@ -216,12 +453,23 @@ pub fn uci_loop() {
 
         let mut data = input.split_whitespace();
         match data.next() {
-            Some("uci")         => if !*uci_info.find_move_signal.read().unwrap() {println!("id name Persa\nid author Yigit\nuciok")},
+            Some("uci")         => if !*uci_info.find_move_signal.read().unwrap() {println!("id name Persa\nid author Yigit\noption name EvalParamsFile type string default <empty>\noption name EvalFile type string default <empty>\noption name UCI_Chess960 type check default false\nuciok")},
+            // the first command of a CECP gui, the rest of the session is xboard
+            Some("xboard")      => return xboard_loop(uci_info),
             Some("isready")     => if !*uci_info.find_move_signal.read().unwrap() {println!("readyok")},
             Some("go")          => if !*uci_info.find_move_signal.read().unwrap() { go(data, &mut uci_info) },
             Some("position")    => if !*uci_info.find_move_signal.read().unwrap() {position(data, &mut uci_info)} ,
+            Some("eval")        => if !*uci_info.find_move_signal.read().unwrap() {eval(&uci_info)},
+            Some("d") | Some("display") => if !*uci_info.find_move_signal.read().unwrap() {println!("{}", display_position(&uci_info.board, uci_info.chess960))},
+            Some("debug")       => set_debug(data, &mut uci_info),
             Some("stop")        => *uci_info.stop_signal.write().unwrap() = true,
-            Some("ucinewgame")  => uci_info = UciInformation::new(),
+            Some("setoption")   => if !*uci_info.find_move_signal.read().unwrap() {set_option(data, &mut uci_info)},
+            Some("saveparams")  => save_params(data, &uci_info),
+            Some("perftsuite")  => if !*uci_info.find_move_signal.read().unwrap() {run_perft_suite(data)},
+            Some("bench")       => if !*uci_info.find_move_signal.read().unwrap() {run_bench(data, &uci_info)},
+            Some("epd")         => if !*uci_info.find_move_signal.read().unwrap() {run_epd_suite(data, &uci_info)},
+            Some("gensfen")     => if !*uci_info.find_move_signal.read().unwrap() {generate_data(data, &uci_info)},
+            Some("ucinewgame")  => uci_info = UciInformation::new().set_eval_params(uci_info.eval_params).set_nnue(uci_info.nnue.clone()).set_chess960(uci_info.chess960).set_debug(uci_info.debug),
             Some("quit")        => *uci_info.quit_signal.write().unwrap() = true,
             _                   => println!("unkown argument"),
         }
@@ -0,0 +1,296 @@
+use std::str::SplitWhitespace;
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+use std::time::Instant;
+use crate::board_components::Color;
+use crate::epd::get_mate_moves;
+use crate::eveluation::find_best_move;
+use crate::game::{Game, GameStatus};
+use crate::pieces::pieces_controller::{BoardHistory, MoveBitField};
+use crate::san::{legal_moves, san_to_move};
+use crate::uci::UciInformation;
+use crate::position::Position;
+
+// CECP (xboard) front-end over the same search as uci_loop. Moves are read as coordinates or SAN and sent as
+// coordinates, the clocks only decide the time limit of every search.
+
+pub const XBOARD_FEATURES: &str = "feature myname=\"Persa\" ping=1 setboard=1 usermove=1 san=0 time=1 draw=0 sigint=0 sigterm=0 \
+    reuse=1 analyze=1 colors=0 variants=\"normal\" done=1";
+
+// without st, level or clocks a search stops at this depth
+const DEFAULT_DEPTH: isize = 5;
+const UNLIMITED_DEPTH: isize = 64;
+// the remaining time is split over this many moves when the level has no move count
+const DEFAULT_MOVES_TO_GO: usize = 30;
+
+pub struct XBoardState {
+    pub uci_info:          UciInformation,
+    pub game:              Game,
+    // the color the engine plays, None in force mode
+    pub engine_color:      Option<Color>,
+    pub post:              bool,
+    pub analyze:           bool,
+    // level <moves per session> <base> <increment>, times in milliseconds
+    pub moves_per_session: usize,
+    pub base_time:         usize,
+    pub increment:         usize,
+    // st, a fixed time for every move in milliseconds
+    pub move_time:         Option<usize>,
+    // sd
+    pub depth_limit:       Option<isize>,
+    // clocks from time and otim in centiseconds
+    pub engine_time:       Option<usize>,
+    pub opponent_time:     Option<usize>,
+    search:                Option<JoinHandle<Option<MoveBitField>>>,
+    // a search stopped by force, new or result sends no move, the thread reads it right before sending one
+    cancel:                Arc<RwLock<bool>>,
+}
+
+impl XBoardState {
+    pub fn new(uci_info: UciInformation) -> Self {
+        Self {
+            uci_info,
+            game:              Game::new(),
+            engine_color:      Some(Color::Black),
+            post:              false,
+            analyze:           false,
+            moves_per_session: 0,
+            base_time:         0,
+            increment:         0,
+            move_time:         None,
+            depth_limit:       None,
+            engine_time:       None,
+            opponent_time:     None,
+            search:            None,
+            cancel:            Arc::new(RwLock::new(false)),
+        }
+    }
+
+    pub fn is_searching(&self) -> bool { self.search.is_some() }
+
+    // st wins over the clock, the clock falls back to the base time of the level before the first time command
+    pub fn get_time_limit(&self) -> Option<usize> {
+        if let Some(move_time) = self.move_time { return Some(move_time); }
+        let time_left = match self.engine_time {
+            Some(centiseconds) => centiseconds * 10,
+            None if self.base_time > 0 => self.base_time,
+            None => return None,
+        };
+        let moves_to_go = if self.moves_per_session > 0 {
+            self.moves_per_session - (self.game.get_board().get_full_move() - 1) % self.moves_per_session
+        } else { DEFAULT_MOVES_TO_GO };
+        Some(usize::min(time_left / moves_to_go + self.increment, time_left / 2))
+    }
+
+    // returns false after quit
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let mut data = line.split_whitespace();
+        match data.next() {
+            Some("protover")  => if data.next().and_then(|version| version.parse::<usize>().ok()).is_some_and(|version| version >= 2) {
+                println!("{}", XBOARD_FEATURES);
+            },
+            Some("new")       => {
+                self.finish_search(true);
+                self.game = Game::new();
+                self.engine_color = Some(Color::Black);
+                self.depth_limit = None;
+                (self.engine_time, self.opponent_time) = (None, None);
+                self.think();
+            },
+            Some("force")     => {
+                self.finish_search(true);
+                self.engine_color = None;
+            },
+            Some("go")        => {
+                self.finish_search(true);
+                self.engine_color = Some(self.game.get_board().get_color());
+                self.think();
+            },
+            Some("playother") => {
+                self.finish_search(true);
+                self.engine_color = Some(self.game.get_board().get_other_color());
+            },
+            Some("usermove")  => self.user_move(data),
+            Some("level")     => self.set_level(data),
+            Some("st")        => match data.next().map(str::parse::<f64>) {
+                Some(Ok(seconds)) => self.move_time = Some((seconds * 1000.0) as usize),
+                _ => println!("Error (st needs seconds): {}", line),
+            },
+            Some("sd")        => match data.next().map(str::parse) {
+                Some(Ok(depth)) => self.depth_limit = Some(depth),
+                _ => println!("Error (sd needs a depth): {}", line),
+            },
+            Some("time")      => self.engine_time = data.next().and_then(|time| time.parse().ok()),
+            Some("otim")      => self.opponent_time = data.next().and_then(|time| time.parse().ok()),
+            Some("undo")      => {
+                self.finish_search(true);
+                self.game.pop();
+                self.think();
+            },
+            Some("remove")    => {
+                self.finish_search(true);
+                self.game.pop();
+                self.game.pop();
+                self.think();
+            },
+            Some("setboard")  => self.set_board(data),
+            Some("post")      => self.post = true,
+            Some("nopost")    => self.post = false,
+            Some("analyze")   => {
+                self.finish_search(true);
+                self.analyze = true;
+                self.think();
+            },
+            Some("exit")      => {
+                self.finish_search(true);
+                self.analyze = false;
+            },
+            // the game is over, the engine waits for new
+            Some("result")    => {
+                self.finish_search(true);
+                self.engine_color = None;
+            },
+            // move now, the search sends the best move it has
+            Some("?")         => if !self.analyze { *self.uci_info.stop_signal.write().unwrap() = true },
+            Some("ping")      => {
+                if !self.analyze { self.finish_search(false); }
+                println!("pong {}", data.next().unwrap_or(""));
+            },
+            Some("variant")   => if data.next() != Some("normal") { println!("Error (unsupported variant): {}", line) },
+            Some("quit")      => {
+                self.finish_search(true);
+                return false;
+            },
+            Some("xboard") | Some("accepted") | Some("rejected") | Some("random") | Some("hard") | Some("easy") | Some("computer") |
+            Some("name") | Some("rating") | Some("ics") | Some(".") | Some("hint") | Some("bk") | None => {},
+            Some(_)           => println!("Error (unknown command): {}", line),
+        }
+        true
+    }
+
+    fn user_move(&mut self, mut data: SplitWhitespace) {
+        let Some(name) = data.next() else { return println!("Error (usermove needs a move): usermove") };
+        self.finish_search(true);
+        match san_to_move(self.game.get_board(), name) {
+            Ok(mov) => {
+                let _ = self.game.push(mov);
+                self.think();
+            },
+            Err(_) => println!("Illegal move: {}", name),
+        }
+    }
+
+    // level 40 5 0, level 0 0:30 2, the base is in minutes (or minutes:seconds) and the increment in seconds
+    fn set_level(&mut self, mut data: SplitWhitespace) {
+        let moves_per_session = data.next().and_then(|moves| moves.parse().ok());
+        let base_time = data.next().and_then(|base| match base.split_once(':') {
+            Some((minutes, seconds)) => Some(minutes.parse::<usize>().ok()? * 60000 + seconds.parse::<usize>().ok()? * 1000),
+            None => Some(base.parse::<usize>().ok()? * 60000),
+        });
+        let increment = data.next().and_then(|increment| increment.parse::<f64>().ok());
+        match (moves_per_session, base_time, increment) {
+            (Some(moves_per_session), Some(base_time), Some(increment)) => {
+                (self.moves_per_session, self.base_time, self.increment) = (moves_per_session, base_time, (increment * 1000.0) as usize);
+                self.move_time = None;
+            },
+            _ => println!("Error (level needs moves, base and increment): level"),
+        }
+    }
+
+    fn set_board(&mut self, data: SplitWhitespace) {
+        self.finish_search(true);
+        // Position also turns down a side not to move that is in check, the search can not handle that
+        match Position::from_fen(&data.collect::<Vec<&str>>().join(" ")) {
+            Ok(position) => {
+                self.game = Game::from_board(*position.get_board());
+                self.think();
+            },
+            Err(_) => println!("tellusererror Illegal position"),
+        }
+    }
+
+    // starts the analysis, or the search when it is the engine's turn, a finished game is claimed instead
+    fn think(&mut self) {
+        let color = self.game.get_board().get_color();
+        if !self.analyze && self.engine_color != Some(color) { return; }
+        if !self.analyze && self.game.outcome(false).is_some() {
+            let status = self.game.status();
+            return println!("{} {{{}}}", self.game.outcome(false).unwrap().get_name(), get_status_comment(status));
+        }
+        if self.game.legal_moves().is_empty() { return; }
+
+        let time_limit = if self.analyze { None } else { self.get_time_limit() };
+        let mut uci_info = self.uci_info.copy().set_board(*self.game.get_board())
+            .set_report_iteration(if self.post || self.analyze { Some(print_thinking) } else { None });
+        uci_info.depth_limit = match (self.analyze, self.depth_limit) {
+            (false, Some(depth)) => depth,
+            (false, None) if time_limit.is_none() => DEFAULT_DEPTH,
+            _ => UNLIMITED_DEPTH,
+        };
+        uci_info.time_limit = time_limit;
+        uci_info.nodes_limit = None;
+        uci_info.node_count = 0;
+        uci_info.board_history = BoardHistory::new();
+        uci_info.start_time = Instant::now();
+        *self.uci_info.stop_signal.write().unwrap() = false;
+        *self.cancel.write().unwrap() = false;
+
+        let (cancel, analyze) = (self.cancel.clone(), self.analyze);
+        self.search = Some(std::thread::spawn(move || {
+            let board = uci_info.board;
+            find_best_move(&mut uci_info);
+            let cancel = cancel.read().unwrap();
+            if analyze || *cancel { return None; }
+            // stopped before the first depth finished
+            let mut mov = uci_info.board_history.found_best_move;
+            if mov == MoveBitField::NO_MOVE { mov = legal_moves(&board)[0]; }
+            println!("move {}", mov.get_uci_name(uci_info.chess960));
+            Some(mov)
+        }));
+    }
+
+    // waits for the search and plays the move it sent, cancel stops it first so it sends nothing unless it already had
+    pub fn finish_search(&mut self, cancel: bool) {
+        let Some(search) = self.search.take() else { return };
+        if cancel {
+            *self.cancel.write().unwrap() = true;
+            *self.uci_info.stop_signal.write().unwrap() = true;
+        }
+        if let Some(mov) = search.join().unwrap() { let _ = self.game.push(mov); }
+        *self.uci_info.stop_signal.write().unwrap() = false;
+    }
+}
+
+fn get_status_comment(status: GameStatus) -> &'static str {
+    match status {
+        GameStatus::Checkmate(Color::White) => "White mates",
+        GameStatus::Checkmate(Color::Black) => "Black mates",
+        GameStatus::Stalemate               => "Stalemate",
+        GameStatus::FiftyMove(_)            => "Fifty move rule",
+        GameStatus::Threefold(_)            => "Threefold repetition",
+        GameStatus::InsufficientMaterial    => "Insufficient material",
+        GameStatus::Ongoing                 => "",
+    }
+}
+
+// ply score time nodes pv, the time is in centiseconds and a mate in n moves is scored 100000 + n
+fn print_thinking(uci_info: &UciInformation) {
+    let history = &uci_info.board_history;
+    let score = match get_mate_moves(history.found_best_score, history.found_best_depth) {
+        Some(moves) => 100000 * moves.signum() + moves,
+        None => history.found_best_score,
+    };
+    println!("{} {} {} {} {}", history.found_best_depth, score, uci_info.start_time.elapsed().as_millis() / 10,
+        uci_info.node_count, history.found_best_move.get_uci_name(uci_info.chess960));
+}
+
+pub fn xboard_loop(uci_info: UciInformation) {
+    let mut state = XBoardState::new(uci_info);
+    let mut input = String::new();
+    loop {
+        input.clear();
+        // end of input quits like the quit command
+        if std::io::stdin().read_line(&mut input).unwrap_or(0) == 0 { return state.finish_search(true); }
+        if !state.handle_command(input.trim()) { return; }
+    }
+}
@@ -1,6 +1,27 @@
 use std::{fs, collections::HashMap};
-use persa_chess::{debug::{FenString, perft_driver}, pieces::{tables::init_statics, pieces_controller::{MoveBitField, BoardSlots}}, eveluation::find_best_move, uci::UciInformation};
-use persa_chess::pieces::pieces_controller::{is_square_attacked_black, is_square_attacked_white, MoveList};
+use persa_chess::{debug::{display_position, FenString, perft_driver, perft_diff_terminal}, pieces::{pieces_controller::{MoveBitField, BoardSlots, BoardStatus}}, eveluation::{find_best_move, eveluate, trace_eveluate, EvalTerm}, uci::UciInformation};
+use persa_chess::pieces::pieces_controller::{is_square_attacked_black, is_square_attacked_white, CompactMove, MoveList, ScoredMove};
+use persa_chess::eveluation_params::EvalParams;
+use persa_chess::constants::eveluation_constants::TaperedScore;
+use persa_chess::tuner::parse_entry;
+use persa_chess::nnue::{Network, NnueState, HIDDEN_SIZE, INPUT_SIZE};
+use persa_chess::board_components::MagicNumGenerator;
+use persa_chess::constants::squares::{A1, A8, B2, C3, D4, E8, G7, H1, H8};
+use persa_chess::gensfen::{play_game, GensfenOptions};
+use persa_chess::perft::{perft_divide, perft_stats, PerftStats, PerftTable};
+use persa_chess::bench::{bench, BENCH_POSITIONS};
+use persa_chess::san::{legal_moves, move_to_san, san_to_move};
+use persa_chess::epd::{get_mate_moves, run_epd, EpdEntry, EpdLimits};
+use persa_chess::game::{DrawClaim, Game, GameResult, GameStatus};
+use persa_chess::{Color, Error, File, Piece, PieceKind, Position, Rank, Square};
+use persa_chess::board_components::{BitBoard, MagicNum};
+use persa_chess::constants::directions::{NORTH, NORTH_WEST, SOUTH, SOUTH_EAST, WEST};
+use persa_chess::render::{BoardRenderer, RenderStyle};
+use persa_chess::xboard::XBoardState;
+use persa_chess::magics::{search_magics, MagicOptions, MagicSet, Slider};
+use persa_chess::pieces::tables::{bishop_attacks_on_fly, generate_bishop_attacks_with, generate_rook_attacks_with, is_pext_available, mask_bishop_attacks, mask_rook_attacks, rook_attacks_on_fly, SliderBackend};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 
 #[test]
@@ -8,7 +29,6 @@ pub fn test_pertfs() {
     let contents = fs::read_to_string("./perfts.txt")
         .expect("Should have been able to read the file");
     
-    init_statics();
     for part in contents.split("\n") {
         let data: Vec<&str> = part.split(";").collect();
         let fen = data[0];
@@ -24,7 +44,6 @@ pub fn test_pertfs() {
 
 #[test]
 pub fn find_mate() {
-    init_statics();
     let fens = [
         "2k5/5Q2/K7/8/8/8/8/8 b - - 5 4", 
         "8/8/KQ2k3/2R5/8/8/8/8 b - - 0 0",
@@ -75,7 +94,6 @@ pub fn find_mate() {
 // Attackers: Pawn Knight Bishop Rook Queen King
 #[test]
 pub fn test_score_move_captures() {
-    init_statics();
     let mut results: HashMap<String, isize> = HashMap::new();
     results.insert("d5e4".to_string(), 10005);
     results.insert("e5d4".to_string(), 10005);
@@ -97,7 +115,206 @@ pub fn test_score_move_captures() {
     let board = FenString::new("3r1k2/pppQ1pp1/2n2nr1/1N1pp1Bp/1b1PPNb1/2Pq2RP/PP3PP1/4KB1R b K - 17 16".to_string()).convert_to_board();
     let uci_info = UciInformation::new().set_board(board);
     for mov in MoveList::new(&uci_info).iterate_moves().filter(|mov| mov.is_move_capture()) {
-        assert_eq!(results[&mov.get_move_name()], mov.get_score() as isize);
+        assert_eq!(results[&mov.get_move_name()], ScoredMove::new(&uci_info, mov).score as isize);
+    }
+}
+
+#[test]
+pub fn test_eval_trace_matches_eveluate() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "3r1k2/pppQ1pp1/2n2nr1/1N1pp1Bp/1b1PPNb1/2Pq2RP/PP3PP1/4KB1R b K - 17 16",
+        "8/8/KQ2k3/2R5/8/8/8/8 b - - 0 0",
+    ];
+    for fen in fens {
+        let board = FenString::new(fen.to_string()).convert_to_board();
+        let trace = trace_eveluate(&board, &EvalParams::new());
+        let total = EvalTerm::ALL.iter().fold(TaperedScore::default(), |total, &term| {
+            total + trace.get_term(term, Color::White) - trace.get_term(term, Color::Black)
+        });
+        let score = match board.get_color() {
+            Color::White => total.taper(trace.phase),
+            Color::Black => -total.taper(trace.phase),
+        };
+        assert_eq!(score, eveluate(&board, &EvalParams::new()));
+    }
+}
+
+#[test]
+pub fn test_eval_color_symmetry() {
+    let positions = [
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1"),
+        ("3r1k2/pppQ1pp1/2n2nr1/1N1pp1Bp/1b1PPNb1/2Pq2RP/PP3PP1/4KB1R b K - 17 16", "4kb1r/pp3pp1/2pQ2rp/1B1ppnB1/1n1PP1bP/2N2NR1/PPPq1PP1/3R1K2 w k - 17 16"),
+        ("4k3/3p4/8/8/7p/2P5/2P5/4K3 w - - 0 1", "4k3/2p5/2p5/7P/8/8/3P4/4K3 b - - 0 1"),
+    ];
+    // a tuned square table moves both colors together
+    let mut params = EvalParams::new();
+    assert!(params.set_param("king_square_score[e1]", 40));
+    assert!(params.set_param("knight_square_score[d4]", -15));
+    assert_eq!(params.get_param("king_score"), None);
+    for (fen, mirrored) in positions {
+        let board = FenString::new(fen.to_string()).convert_to_board();
+        let mirrored = FenString::new(mirrored.to_string()).convert_to_board();
+        assert_eq!(eveluate(&board, &params), eveluate(&mirrored, &params));
+    }
+}
+
+#[test]
+pub fn test_eval_params_round_trip() {
+    let mut params = EvalParams::new();
+    assert_eq!(params.get_param("pawn_score"), Some(100));
+    assert_eq!(params.get_param("knight_square_score[d4]"), Some(30));
+    assert!(params.set_param("rook_mobility[3].eg", 7));
+    assert!(!params.set_param("no_such_param", 7));
+
+    let mut loaded = EvalParams::new();
+    loaded.parse(&params.to_string()).unwrap();
+    assert_eq!(loaded.get_values(), params.get_values());
+    assert_eq!(loaded.get_param("rook_mobility[3].eg"), Some(7));
+    assert_eq!(loaded.get_names().len(), loaded.get_values().len());
+    assert!(loaded.parse("pawn_score = abc").is_err());
+}
+
+#[test]
+pub fn test_tuner_parse_entry() {
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string();
+    assert_eq!(parse_entry("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - c9 \"1-0\";"), Some((start.clone(), 1.0)));
+    assert_eq!(parse_entry("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 12 40 [0.5]"), Some((start.clone(), 0.5)));
+    assert_eq!(parse_entry("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1,0.0"), Some((start.clone(), 0.0)));
+    assert_eq!(parse_entry("# comment"), None);
+    assert_eq!(parse_entry("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - c9 \"*\";"), None);
+}
+
+#[test]
+pub fn test_move_counters() {
+    persa_chess::init_all_statics();
+    let play = |board: persa_chess::pieces::pieces_controller::BoardStatus, name: &str| {
+        let mut board = board;
+        let mov = MoveList::new(&UciInformation::new().set_board(board)).iterate_moves().find(|mov| mov.get_move_name() == name).unwrap();
+        let _ = board.make_move(mov);
+        board
+    };
+    let board = FenString::new("r3k3/8/8/8/8/8/4P3/R3K2N w - - 7 12".to_string()).convert_to_board();
+    // a quiet move counts up, a capture or a pawn move resets, black's move ends the full move
+    let quiet = play(board, "h1g3");
+    assert_eq!((quiet.get_half_move(), quiet.get_full_move()), (8, 12));
+    let capture = play(quiet, "a8a1");
+    assert_eq!((capture.get_half_move(), capture.get_full_move()), (0, 13));
+    let pawn = play(board, "e2e4");
+    assert_eq!((pawn.get_half_move(), pawn.get_full_move()), (0, 12));
+    let reply = play(pawn, "e8d8");
+    assert_eq!((reply.get_half_move(), reply.get_full_move()), (1, 13));
+}
+
+#[test]
+pub fn test_search_ply_and_score() {
+    persa_chess::init_all_statics();
+    // killers are stored by distance from the root, a long game no longer indexes past the table
+    let board = FenString::new("6k1/5ppp/8/8/8/8/8/R5K1 w - - 80 60".to_string()).convert_to_board();
+    let mut uci_info = UciInformation::new().set_board(board).set_depth_limit(3);
+    find_best_move(&mut uci_info);
+    assert_eq!(uci_info.ply, 0);
+    assert_eq!(uci_info.board_history.found_best_move.get_move_name(), "a1a8");
+    assert!(uci_info.board_history.found_best_score >= 50000);
+    uci_info.board_history.append_killer_move(uci_info.board_history.found_best_move, 64);
+}
+
+#[test]
+pub fn test_search_node_count() {
+    persa_chess::init_all_statics();
+    // nodes add up over the iterations, the caller starts the count
+    let board = FenString::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string()).convert_to_board();
+    let search = |start: usize| {
+        let mut uci_info = UciInformation::new().set_board(board).set_depth_limit(2);
+        uci_info.node_count = start;
+        find_best_move(&mut uci_info);
+        uci_info.node_count
+    };
+    let nodes = search(0);
+    assert_eq!(search(1000), 1000 + nodes);
+
+    let mut limited = UciInformation::new().set_board(board).set_depth_limit(10).set_nodes_limit(nodes);
+    find_best_move(&mut limited);
+    assert!(limited.node_count <= nodes + 1);
+}
+
+#[test]
+pub fn test_movetime_limit() {
+    persa_chess::init_all_statics();
+    // a movetime search runs until its time is used, not only while it is already over
+    let board = FenString::new("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string()).convert_to_board();
+    let mut uci_info = UciInformation::new().set_board(board).set_depth_limit(3);
+    uci_info.time_limit = Some(60000);
+    assert!(!uci_info.is_search_fnished());
+    find_best_move(&mut uci_info);
+    assert_eq!(uci_info.board_history.found_best_move.get_move_name(), "a1a8");
+    uci_info.time_limit = Some(0);
+    assert!(uci_info.is_search_fnished());
+}
+
+fn random_network_bytes() -> Vec<u8> {
+    let mut generator = MagicNumGenerator::new();
+    let mut bytes = b"PRSN".to_vec();
+    bytes.extend((HIDDEN_SIZE as u32).to_le_bytes());
+    for _ in 0..(INPUT_SIZE * HIDDEN_SIZE + HIDDEN_SIZE + 2 * HIDDEN_SIZE) {
+        let weight = (generator.get_random_u32() % 128) as i16 - 64;
+        bytes.extend(weight.to_le_bytes());
+    }
+    bytes.extend(25i32.to_le_bytes());
+    bytes
+}
+
+#[test]
+pub fn test_nnue_output_does_not_overflow() {
+    // every hidden value clamps to the maximum and every output weight is i16::MAX,
+    // the two dot products add up past i32::MAX
+    let mut bytes = b"PRSN".to_vec();
+    bytes.extend((HIDDEN_SIZE as u32).to_le_bytes());
+    bytes.extend(std::iter::repeat_n(0i16.to_le_bytes(), INPUT_SIZE * HIDDEN_SIZE).flatten());
+    bytes.extend(std::iter::repeat_n(1000i16.to_le_bytes(), HIDDEN_SIZE).flatten());
+    bytes.extend(std::iter::repeat_n(i16::MAX.to_le_bytes(), 2 * HIDDEN_SIZE).flatten());
+    bytes.extend(0i32.to_le_bytes());
+    let board = FenString::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string()).convert_to_board();
+    let expected = (2 * HIDDEN_SIZE as i64 * 255 * i16::MAX as i64 * 400 / (255 * 64)) as isize;
+    for simd in [false, true] {
+        let mut network = Network::from_bytes(&bytes).unwrap();
+        network.set_simd(simd);
+        let mut state = NnueState::new(Arc::new(network));
+        state.reset(&board);
+        assert_eq!(state.eveluate(Color::White), expected);
+    }
+}
+
+#[test]
+pub fn test_nnue_incremental_matches_refresh() {
+    let bytes = random_network_bytes();
+    let mut scalar = Network::from_bytes(&bytes).unwrap();
+    scalar.set_simd(false);
+    let mut simd_state   = NnueState::new(Arc::new(Network::from_bytes(&bytes).unwrap()));
+    let mut scalar_state = NnueState::new(Arc::new(scalar));
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    ];
+    for fen in fens {
+        let root = FenString::new(fen.to_string()).convert_to_board();
+        for mov in MoveList::new(&UciInformation::new().set_board(root)).iterate_moves() {
+            let mut board = root;
+            if board.make_move(mov).is_none() { continue; }
+            for state in [&mut simd_state, &mut scalar_state] {
+                state.reset(&root);
+                state.push(&board);
+            }
+            let mut fresh = NnueState::new(Arc::new(Network::from_bytes(&bytes).unwrap()));
+            fresh.reset(&board);
+            assert_eq!(simd_state.get_accumulator().values, fresh.get_accumulator().values, "{}", mov);
+            assert_eq!(scalar_state.get_accumulator().values, fresh.get_accumulator().values, "{}", mov);
+            assert_eq!(simd_state.eveluate(board.get_color()), scalar_state.eveluate(board.get_color()));
+            simd_state.pop();
+            scalar_state.pop();
+        }
     }
 }
 
@@ -105,3 +322,496 @@ pub fn uci_tests () {
     
 }
 
+
+#[test]
+pub fn test_gensfen_game() {
+    let mut options = GensfenOptions::new();
+    options.depth = 2;
+    options.random_plies = 6;
+    options.max_plies = 80;
+    let book = vec![FenString::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string()).convert_to_board()];
+
+    let lines = play_game(&mut UciInformation::new(), &options, &book, &mut MagicNumGenerator::from_seed(42));
+    assert!(!lines.is_empty());
+    for line in &lines {
+        let fields: Vec<&str> = line.split(" | ").collect();
+        assert_eq!(fields.len(), 3);
+        assert!(!FenString::new(fields[0].to_string()).convert_to_board().is_in_check());
+        assert!(fields[1].parse::<isize>().is_ok());
+        assert!(["1.0", "0.5", "0.0"].contains(&fields[2]));
+    }
+    assert_eq!(lines, play_game(&mut UciInformation::new(), &options, &book, &mut MagicNumGenerator::from_seed(42)));
+
+    // a lone bishop can not mate, the game is a draw before any position is recorded
+    options.random_plies = 0;
+    let book = vec![FenString::new("8/8/4k3/8/8/2B5/8/4K3 w - - 0 1".to_string()).convert_to_board()];
+    assert!(play_game(&mut UciInformation::new(), &options, &book, &mut MagicNumGenerator::from_seed(42)).is_empty());
+}
+
+fn check_make_unmake(board: &mut BoardStatus, depth: usize) {
+    if depth == 0 { return; }
+    let fen = FenString::from_board(board).get_fen_string();
+    let hash = board.get_hash();
+    for mov in MoveList::new(&UciInformation::new().set_board(*board)).iterate_moves() {
+        let Some(undo) = board.make_move(mov) else {
+            assert_eq!(FenString::from_board(board).get_fen_string(), fen);
+            continue;
+        };
+        assert_eq!(board.get_hash(), board.compute_hash(), "{} after {}", fen, mov.get_move_name());
+        check_make_unmake(board, depth - 1);
+        board.unmake_move(mov, &undo);
+        assert_eq!(FenString::from_board(board).get_fen_string(), fen, "unmake {}", mov.get_move_name());
+        assert_eq!(board.get_hash(), hash);
+    }
+}
+
+#[test]
+pub fn test_make_unmake_restores_board() {
+    let contents = fs::read_to_string("./perfts.txt").unwrap();
+    for part in contents.lines() {
+        let fen = part.split(';').next().unwrap();
+        check_make_unmake(&mut FenString::new(fen.to_string()).convert_to_board(), 3);
+    }
+}
+
+#[test]
+pub fn test_piece_on_and_consistency() {
+    let mut board = FenString::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_string()).convert_to_board();
+    assert_eq!(board.piece_on(E8), Some(BoardSlots::BlackKing));
+    assert_eq!(board.piece_on(H1), Some(BoardSlots::WhiteRook));
+    assert_eq!(board.piece_on(D4), None);
+    assert!(board.check_consistency().is_ok());
+
+    board[BoardSlots::WhitePawn].set_bit(D4);
+    assert!(board.check_consistency().is_err());
+}
+
+#[test]
+pub fn test_perfts_960() {
+    let contents = fs::read_to_string("./perfts960.txt").unwrap();
+    for part in contents.lines() {
+        let data: Vec<&str> = part.split(";").collect();
+        let board = FenString::new(data[0].to_string()).convert_to_board();
+        for i in 1..5 {
+            let uci_info = UciInformation::new().set_chess960(true).set_board(board).set_depth_limit(i);
+            let perft = data[i as usize].split(" ").nth(1).unwrap();
+            assert_eq!(perft_driver(&uci_info), perft.parse().unwrap(), "{} depth {}", data[0], i);
+        }
+    }
+}
+
+#[test]
+pub fn test_chess960_fen_and_castling() {
+    let fen = "qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9";
+    let board = FenString::new(fen.to_string()).convert_to_board();
+    let x_fen = "qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w KQkq - 1 9";
+    assert_eq!(FenString::from_board(&board).get_fen_string(), x_fen);
+    assert_eq!(FenString::from_board_with(&board, true).get_fen_string(), fen);
+    assert_eq!(FenString::from_board_with(&FenString::new(x_fen.to_string()).convert_to_board(), true).get_fen_string(), fen);
+
+    let uci_info = UciInformation::new().set_board(board);
+    let castles: Vec<MoveBitField> = MoveList::new(&uci_info).iterate_moves().filter(MoveBitField::is_move_castling).collect();
+    assert_eq!(castles.len(), 1);
+    assert_eq!(castles[0].get_uci_name(true), "g1h1");
+}
+
+#[test]
+pub fn test_perft_hash_and_threads() {
+    let board = FenString::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_string()).convert_to_board();
+    let table = PerftTable::new(16);
+    let result = perft_divide(&board, 4, 2, Some(&table), false);
+    assert_eq!(result.nodes, 4085603);
+    assert_eq!(result.divide.len(), 48);
+    assert!(result.divide.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert_eq!(perft_divide(&board, 4, 1, Some(&table), false).nodes, 4085603);
+}
+
+#[test]
+pub fn test_perft_stats_kiwipete() {
+    let board = FenString::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_string()).convert_to_board();
+    let stats = |nodes, captures, en_passant, castles, promotions, checks, discovered_checks, double_checks, checkmates| PerftStats {
+        nodes, captures, en_passant, castles, promotions, checks, discovered_checks, double_checks, checkmates
+    };
+    assert_eq!(perft_stats(&board, 4), vec![
+        stats(48,      8,      0,    2,      0,     0,     0,  0, 0),
+        stats(2039,    351,    1,    91,     0,     3,     0,  0, 0),
+        stats(97862,   17102,  45,   3162,   0,     993,   0,  0, 1),
+        stats(4085603, 757163, 1929, 128013, 15172, 25523, 42, 6, 43),
+    ]);
+}
+
+#[test]
+pub fn test_perftree_arguments() {
+    let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string();
+    assert_eq!(perft_diff_terminal(&["2".to_string(), fen.clone()]), Ok(400));
+    assert_eq!(perft_diff_terminal(&["2".to_string(), fen.clone(), "".to_string()]), Ok(400));
+    assert_eq!(perft_diff_terminal(&["2".to_string(), fen.clone(), "e2e4 e7e5".to_string()]), Ok(835));
+    assert!(perft_diff_terminal(&["2".to_string(), fen, "e2e5".to_string()]).is_err());
+}
+
+#[test]
+pub fn test_bench_is_deterministic() {
+    let single = bench(3, 1, EvalParams::new(), None);
+    let threaded = bench(3, 2, EvalParams::new(), None);
+    assert_eq!(single.positions.len(), BENCH_POSITIONS.len());
+    assert!(single.nodes > 0);
+    assert_eq!(single.nodes, threaded.nodes);
+    assert_eq!(single.positions, threaded.positions);
+    for fen in BENCH_POSITIONS {
+        let board = FenString::new(fen.to_string()).convert_to_board();
+        assert_eq!(FenString::from_board(&board).get_fen_string(), fen);
+        assert_eq!(board.check_consistency(), Ok(()));
+    }
+}
+
+#[test]
+pub fn test_san_and_epd() {
+    let board = FenString::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_string()).convert_to_board();
+    let names: Vec<String> = legal_moves(&board).into_iter().map(|mov| move_to_san(&board, mov)).collect();
+    for name in ["O-O", "O-O-O", "Qxf6", "Bxa6", "Nxf7", "Qxh3", "gxh3", "Nd1", "d6", "Rb1"] {
+        assert!(names.contains(&name.to_string()), "{} missing from {:?}", name, names);
+    }
+    for mov in legal_moves(&board) {
+        assert!(san_to_move(&board, &move_to_san(&board, mov)) == Ok(mov));
+    }
+    let board = FenString::new("4k3/8/8/R7/8/8/8/RN2KN1Q w - - 0 1".to_string()).convert_to_board();
+    let names: Vec<String> = legal_moves(&board).into_iter().map(|mov| move_to_san(&board, mov)).collect();
+    for name in ["Nbd2", "Nfd2", "R1a3", "R5a3", "Qh4", "Qe4+", "O-O-O"] {
+        assert!(names.contains(&name.to_string()) != (name == "O-O-O"), "{} in {:?}", name, names);
+    }
+    let board = FenString::new("7k/1P6/8/8/8/8/8/4R1K1 w - - 0 1".to_string()).convert_to_board();
+    assert_eq!(move_to_san(&board, san_to_move(&board, "b8Q").unwrap()), "b8=Q+");
+    assert_eq!(move_to_san(&board, san_to_move(&board, "e1e2").unwrap()), "Re2");
+    let board = FenString::new("k7/8/1K6/8/8/8/8/7R w - - 0 1".to_string()).convert_to_board();
+    assert_eq!(move_to_san(&board, san_to_move(&board, "Rh8").unwrap()), "Rh8#");
+    assert!(san_to_move(&board, "Rxa8").is_err());
+
+    let entry = EpdEntry::parse("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\"; c0 \"a; b\"; hmvc 4;").unwrap();
+    assert_eq!(entry.get_id(), Some("WAC.001"));
+    assert_eq!(entry.get("c0"), Some(&vec!["a; b".to_string()]));
+    assert_eq!(entry.board.get_half_move(), 4);
+    assert_eq!(entry.get_epd_string(), "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\"; c0 \"a; b\"; hmvc 4;");
+    assert!(EpdEntry::parse("8/8/8/8/8/8/8/K6k w - - bm Ka2").is_err());
+
+    let path = std::env::temp_dir().join("persa_test_suite.epd");
+    let output = std::env::temp_dir().join("persa_test_result.epd");
+    fs::write(&path, "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; dm 2; id \"WAC.001\";\n4k3/8/8/8/3q4/8/8/3R3K b - - bm Qxd1+;\n").unwrap();
+    let results = run_epd(&[path.to_str().unwrap()], &EpdLimits::new().set_depth(4), EvalParams::new(), None, output.to_str()).unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!((results[0].suite.as_str(), results[0].best_move.as_str(), results[0].passed), ("WAC", "Qg6", Some(true)));
+    assert_eq!((results[1].suite.as_str(), results[1].best_move.as_str(), results[1].passed), ("persa_test_suite", "Qxd1+", Some(true)));
+    assert_eq!(get_mate_moves(results[0].score, results[0].depth), Some(2));
+    let written = EpdEntry::parse(fs::read_to_string(&output).unwrap().lines().next().unwrap()).unwrap();
+    assert_eq!(written.get("pm"), Some(&vec!["Qg6".to_string()]));
+    assert_eq!(written.get("acd"), Some(&vec!["4".to_string()]));
+}
+
+#[test]
+pub fn test_game_status() {
+    let mut game = Game::new();
+    assert_eq!(game.status(), GameStatus::Ongoing);
+    assert_eq!(game.legal_moves().len(), 20);
+    let push = |game: &mut Game, name: &str| {
+        let mov = san_to_move(game.get_board(), name).unwrap();
+        game.push(mov).unwrap();
+    };
+    for name in ["f3", "e5", "g4", "Qh4#"] { push(&mut game, name); }
+    assert_eq!(game.status(), GameStatus::Checkmate(Color::Black));
+    assert_eq!(game.outcome(false), Some(GameResult::BlackWins));
+    assert_eq!(game.pop().map(|mov| mov.get_move_name()), Some("d8h4".to_string()));
+    assert_eq!(game.status(), GameStatus::Ongoing);
+    let white_move = san_to_move(Game::new().get_board(), "e4").unwrap();
+    assert!(game.push(white_move).is_err());
+
+    let mut game = Game::new();
+    for _ in 0..2 { for name in ["Nf3", "Nf6", "Ng1", "Ng8"] { push(&mut game, name); } }
+    assert_eq!(game.get_repetitions(), 3);
+    assert_eq!(game.status(), GameStatus::Threefold(DrawClaim::Claimable));
+    assert!(game.status().is_claimable() && !game.status().is_game_over());
+    assert_eq!(game.outcome(false), None);
+    assert_eq!(game.outcome(true), Some(GameResult::Draw));
+    for _ in 0..2 { for name in ["Nf3", "Nf6", "Ng1", "Ng8"] { push(&mut game, name); } }
+    assert_eq!(game.status(), GameStatus::Threefold(DrawClaim::Automatic));
+    assert_eq!(game.outcome(false), Some(GameResult::Draw));
+
+    // the en passant square after e4 counts only while a pawn can take on it
+    let mut game = Game::new();
+    for name in ["e4", "Nf6", "Nf3", "Ng8", "Ng1", "Nf6", "Nf3", "Ng8", "Ng1"] { push(&mut game, name); }
+    assert_eq!(game.get_repetitions(), 3);
+    assert_eq!(game.status(), GameStatus::Threefold(DrawClaim::Claimable));
+    let mut game = Game::from_fen("4k1n1/8/8/8/3p4/8/4P3/4K1N1 w - - 0 1");
+    for name in ["e4", "Nf6", "Nf3", "Ng8", "Ng1"] { push(&mut game, name); }
+    assert_eq!(game.get_repetitions(), 1);
+
+    assert_eq!(Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").status(), GameStatus::Stalemate);
+    assert_eq!(Game::from_fen("7k/8/6K1/8/8/8/8/7R w - - 100 80").status(), GameStatus::FiftyMove(DrawClaim::Claimable));
+    assert_eq!(Game::from_fen("7k/8/6K1/8/8/8/8/7R w - - 150 80").status(), GameStatus::FiftyMove(DrawClaim::Automatic));
+    assert_eq!(Game::from_fen("R6k/8/6K1/8/8/8/8/8 b - - 150 80").status(), GameStatus::Checkmate(Color::White));
+    for fen in ["7k/8/6K1/8/8/8/8/8 w - - 0 1", "7k/8/6K1/8/8/8/8/2B5 w - - 0 1", "7k/8/6K1/8/8/8/8/2n5 w - - 0 1", "7k/8/6K1/2b5/8/8/8/2B5 w - - 0 1", "7k/8/6K1/8/8/8/8/B1B5 w - - 0 1"] {
+        assert_eq!(Game::from_fen(fen).status(), GameStatus::InsufficientMaterial, "{}", fen);
+    }
+    for fen in ["7k/8/6K1/8/8/8/8/1BB5 w - - 0 1", "7k/8/6K1/8/8/8/8/1NB5 w - - 0 1", "7k/8/6K1/8/8/8/P7/8 w - - 0 1"] {
+        assert_eq!(Game::from_fen(fen).status(), GameStatus::Ongoing, "{}", fen);
+    }
+}
+
+#[test]
+pub fn test_position_api() {
+    let position = Position::startpos();
+    assert_eq!(position.legal_moves().count(), 20);
+    assert_eq!(position.to_string(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    assert_eq!("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -".parse::<Position>(), Ok(position));
+    assert_eq!(position.piece_on("e1".parse().unwrap()), Some(Piece::new(PieceKind::King, Color::White)));
+    assert_eq!(position.piece_on("e4".parse().unwrap()), None);
+    assert_eq!("q".parse::<Piece>().map(|piece| piece.to_string()), Ok("q".to_string()));
+    assert_eq!("E4".parse::<Square>().map(|square| square.to_string()), Ok("e4".to_string()));
+    assert_eq!("b".parse::<Color>(), Ok(Color::Black));
+    assert!("i9".parse::<Square>().is_err() && "x".parse::<Piece>().is_err());
+
+    let e4 = position.parse_move("e2e4").unwrap();
+    assert_eq!(position.parse_move("e4"), Ok(e4));
+    assert_eq!((e4.get_source().to_string(), e4.get_target().to_string(), e4.get_piece().kind), ("e2".to_string(), "e4".to_string(), PieceKind::Pawn));
+    let after = position.play(e4).unwrap();
+    assert_eq!(after.get_color(), Color::Black);
+    assert_eq!(after.get_enpassant().map(|square| square.to_string()), Some("e3".to_string()));
+    assert_eq!(after.play(e4), Err(Error::IllegalMove("e2e4".to_string())));
+    assert_eq!(position.parse_move("e5"), Err(Error::IllegalMove("e5".to_string())));
+
+    let mut position: Position = "r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1".parse().unwrap();
+    let castle = position.parse_move("O-O-O").unwrap();
+    assert!(castle.is_castling());
+    assert_eq!((castle.to_string(), castle.get_uci_name(true)), ("e1c1".to_string(), "e1a1".to_string()));
+    let promotion = position.parse_move("b7a8q").unwrap();
+    assert_eq!((promotion.get_promotion(), promotion.is_capture()), (Some(PieceKind::Queen), true));
+    assert_eq!(position.get_san(promotion), Ok("bxa8=Q+".to_string()));
+    position.make_move(promotion).unwrap();
+    assert!(position.is_in_check());
+    assert_eq!(position.legal_moves().map(|mov| mov.to_string()).collect::<Vec<_>>(), vec!["e8d7", "e8e7", "e8f7"]);
+
+    for fen in ["", "8/8/8/8/8/8/8/8 w - - 0 1", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+        "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1", "P3k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4K3 w A - 0 1", "4k3/8/8/8/8/8/8/4K2R w E - 0 1", "4k3/8/8/8/8/8/8/R3K3 w K - 0 1", "4k3/8/8/8/8/8/4K3/R6R w Q - 0 1",
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 2"] {
+        assert!(matches!(fen.parse::<Position>(), Err(Error::InvalidFen(_))), "{}", fen);
+    }
+    assert!("4k3/8/8/8/8/8/8/4K2R w H - 0 1".parse::<Position>().is_ok());
+
+    // equality looks at the position, not at how the fen wrote it
+    let castles: Position = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".parse().unwrap();
+    assert_eq!(castles, "r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1".parse().unwrap());
+    assert_ne!(castles, "r3k2r/8/8/8/8/8/8/R3K2R w KQk - 0 1".parse().unwrap());
+    assert_ne!(castles, "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 1 1".parse().unwrap());
+    assert_ne!(castles, "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1".parse().unwrap());
+    assert_eq!(persa_chess::get_moves("4k3/8/8/8/8/8/8/4K3 w - - 0 1".to_string()).len(), 5);
+    assert_eq!(persa_chess::make_move("4k3/8/8/8/8/8/8/4K3 w - - 0 1".to_string(), "Kd1".to_string()), "4k3/8/8/8/8/8/8/3K4 b - - 1 1");
+}
+
+#[test]
+fn test_slider_backends() {
+    // every subset of the relevant occupancy plus the edge squares, which must not change anything
+    assert_eq!(is_pext_available(), cfg!(feature = "pext"));
+    let mut backends = vec![SliderBackend::Magic];
+    if is_pext_available() { backends.push(SliderBackend::Pext); }
+    for square in (0..64).map(Square) {
+        for (mask, on_fly, lookup) in [
+            (mask_rook_attacks(square), rook_attacks_on_fly as fn(Square, BitBoard) -> BitBoard, generate_rook_attacks_with as fn(SliderBackend, Square, BitBoard) -> BitBoard),
+            (mask_bishop_attacks(square), bishop_attacks_on_fly, generate_bishop_attacks_with),
+        ] {
+            let mut occupancy: u64 = 0;
+            loop {
+                let expected = on_fly(square, BitBoard(occupancy));
+                for backend in &backends {
+                    assert_eq!(lookup(*backend, square, BitBoard(occupancy)), expected, "{:?} {} {:x}", backend, square, occupancy);
+                    assert_eq!(lookup(*backend, square, BitBoard(occupancy | !mask.0)), expected, "{:?} {} {:x}", backend, square, occupancy);
+                }
+                occupancy = occupancy.wrapping_sub(mask.0) & mask.0;
+                if occupancy == 0 { break; }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_magic_search() {
+    let (rooks, bishops) = (MagicSet::current(Slider::Rook), MagicSet::current(Slider::Bishop));
+    assert!(rooks.verify().is_ok() && bishops.verify().is_ok());
+    assert_eq!((rooks.get_table_size(), bishops.get_table_size()), (102400, 5248));
+
+    static REPORTED: AtomicUsize = AtomicUsize::new(0);
+    let options = MagicOptions::new().set_seed(7).set_report(|_, _, _, _| { REPORTED.fetch_add(1, Ordering::Relaxed); });
+    let mut found = search_magics(Slider::Bishop, &options).unwrap();
+    assert_eq!(REPORTED.load(Ordering::Relaxed), 64);
+    assert_eq!(found.move_counts.0, bishops.move_counts.0);
+    assert!(found.get_rust_source().contains("pub const fn create_bishop_magics() -> ChessBoard<MagicNum> {"));
+    found.magics[Square(27)] = MagicNum(1);
+    assert_eq!(found.verify(), Err("bishop magics fail on d4".to_string()));
+    assert!(search_magics(Slider::Rook, &MagicOptions::new().set_bits(Some(13))).is_err());
+}
+
+#[test]
+fn test_bitboard_api() {
+    let (a1, h1, d4) = (BitBoard::from(A1), BitBoard::from(H1), BitBoard::from(D4));
+    assert_eq!((a1.west(), a1.south(), a1.south_west(), h1.east(), h1.north_east()), (BitBoard(0), BitBoard(0), BitBoard(0), BitBoard(0), BitBoard(0)));
+    assert_eq!((a1.east(), a1.north(), a1.north_east(), h1.north_west()), (BitBoard::from(Square(1)), BitBoard::from(Square(8)), BitBoard::from(B2), BitBoard::from(Square(14))));
+    assert_eq!(BitBoard::from(H8).south_west().south_east(), BitBoard::from(Square(47)));
+    assert_eq!(d4.north().south().east().west(), d4);
+
+    assert_eq!(d4.north_fill(), BitBoard(0x0808080808000000));
+    assert_eq!(d4.south_fill(), BitBoard(0x0000000008080808));
+    assert_eq!(d4.file_fill(), BitBoard::file_of(D4));
+    assert_eq!(BitBoard::rank_of(D4), BitBoard(0xFF000000));
+    assert_eq!(BitBoard::adjacent_files(D4), BitBoard(0x1414141414141414));
+    assert_eq!(BitBoard::adjacent_files(H8), BitBoard::file_of(G7));
+
+    assert_eq!(BitBoard::between(A1, H8), BitBoard::from(B2) | C3 | D4 | Square(36) | Square(45) | G7);
+    assert_eq!(BitBoard::between(H8, A1), BitBoard::between(A1, H8));
+    assert_eq!((BitBoard::between(A1, B2), BitBoard::between(A1, Square(26))), (BitBoard(0), BitBoard(0)));
+    assert_eq!(BitBoard::between(A1, A8), BitBoard(0x0001010101010100));
+    assert_eq!(BitBoard::line(C3, D4), BitBoard(0x8040201008040201));
+    assert_eq!(BitBoard::line(H1, A1), BitBoard(0xFF));
+    assert_eq!(BitBoard::line(A1, Square(17)), BitBoard(0));
+
+    let board = BitBoard::from(A1) | H8 | D4;
+    assert_eq!((board ^ D4, board - d4, board & D4), (BitBoard::from(A1) | H8, BitBoard::from(A1) | H8, d4));
+    assert_eq!((board.get_lsb_index(), board.get_msb_index()), (A1, H8));
+    assert!(board.more_than_one() && !d4.more_than_one() && !BitBoard(0).more_than_one());
+    assert!(BitBoard(0).is_empty() && (board & BitBoard::from(B2)).is_empty());
+}
+
+#[test]
+fn test_square_types() {
+    let e4 = Square::new(File::E, Rank::Fourth);
+    assert_eq!(e4, Square(28));
+    assert_eq!(("e4".parse::<Square>(), "E4".parse::<Square>()), (Ok(e4), Ok(e4)));
+    for name in ["", "e", "e9", "i4", "e44", "é4"] { assert_eq!(name.parse::<Square>(), Err(Error::InvalidSquare(name.to_string()))); }
+    assert_eq!(("h".parse::<File>(), "8".parse::<Rank>()), (Ok(File::H), Ok(Rank::Eighth)));
+    assert_eq!(("i".parse::<File>(), "9".parse::<Rank>()), (Err(Error::InvalidFile("i".into())), Err(Error::InvalidRank("9".into()))));
+    assert_eq!((format!("{}", e4), format!("{:#}", e4), e4.get_uci_name(), e4.get_name()), ("e4".into(), "E4".into(), "e4".into(), "E4".into()));
+    assert_eq!((File::of(e4), Rank::of(e4), File::E.to_string(), Rank::Fourth.to_string()), (File::E, Rank::Fourth, "e".into(), "4".into()));
+
+    assert_eq!((e4.offset(1, 2), e4.offset(4, 0), e4.offset(0, -4)), (Some(Square(45)), None, None));
+    assert_eq!((e4.offset(127, 0), e4.offset(-128, 0), e4.offset(0, 127), e4.offset(0, -128)), (None, None, None, None));
+    assert_eq!((e4.checked_add(NORTH), e4.checked_add(SOUTH * 2), e4.checked_add(NORTH_WEST)), (Some(Square(36)), Some(Square(12)), Some(Square(35))));
+    assert_eq!((Square(0).checked_add(WEST), Square(7).checked_add(SOUTH_EAST), Square(63).checked_add(NORTH)), (None, None, None));
+
+    assert_eq!((e4.flip_rank(), e4.flip_file(), e4.relative(Color::White), e4.relative(Color::Black)), (Square(36), Square(27), e4, Square(36)));
+    assert_eq!((File::A.mirror(), Rank::Second.flip(), Rank::Second.relative(Color::Black)), (File::H, Rank::Seventh, Rank::Seventh));
+    assert_eq!((File::E.get_bitboard(), Rank::Fourth.get_bitboard()), (BitBoard::file_of(e4), BitBoard::rank_of(e4)));
+    assert_eq!((File::from_index(8), Rank::from_index(7)), (None, Some(Rank::Eighth)));
+}
+
+#[test]
+fn test_compact_move() {
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/1P4P1/8/2pP4/8/8/1p4p1/R3K2R w KQkq c6 0 1",
+        "r3k2r/1P4P1/8/8/2Pp4/8/1p4p1/R3K2R b KQkq c3 0 1",
+        "1rk2r2/8/8/8/8/8/8/1RK2R2 w KQkq - 0 1",
+    ];
+    for fen in fens {
+        let board = FenString::new(fen.to_string()).convert_to_board();
+        let moves = MoveList::generate(&board);
+        for mov in moves.iterate_moves() {
+            let compact = CompactMove::from(mov);
+            assert!(compact.to_bitfield(&board) == Some(mov), "{} {}", fen, mov);
+            assert_eq!(CompactMove::from_bits(compact.get_bits()), compact);
+            assert_eq!((compact.get_source(), compact.get_target()), (mov.get_source(), mov.get_target()));
+            assert_eq!((compact.is_enpassant(), compact.is_castling()), (mov.is_move_enpassant(), mov.is_move_castling()));
+            assert_eq!((compact.get_uci_name(false), compact.get_uci_name(true)), (mov.get_uci_name(false), mov.get_uci_name(true)));
+        }
+        let distinct: std::collections::HashSet<u16> = moves.iterate_moves().map(|mov| CompactMove::from(mov).get_bits()).collect();
+        assert_eq!(distinct.len(), moves.count);
+    }
+    assert!(CompactMove::from_bits(0x0fff).to_bitfield(&FenString::new(fens[0].to_string()).convert_to_board()).is_none());
+
+    // a quiet move that cut off before is tried right after the captures
+    let board = FenString::new(fens[0].to_string()).convert_to_board();
+    let mut uci_info = UciInformation::new().set_board(board);
+    let quiet = MoveList::generate(&board).iterate_moves().find(|mov| mov.get_move_name() == "a2a3").unwrap();
+    uci_info.board_history.append_killer_move(quiet, 0);
+    assert_eq!(ScoredMove::new(&uci_info, quiet).score, 9000);
+    let ordered = MoveList::new(&uci_info);
+    let killer_index = ordered.iterate_moves().position(|mov| mov == quiet).unwrap();
+    assert!(ordered.iterate_moves().take(killer_index).all(|mov| mov.is_move_capture()));
+    assert!(ordered.iterate_moves().skip(killer_index + 1).all(|mov| ScoredMove::new(&uci_info, mov).score < 9000));
+}
+
+#[test]
+fn test_render() {
+    let board = FenString::new("4k3/8/8/8/4P3/8/8/4K2R b K e3 0 1".to_string()).convert_to_board();
+    let ascii = BoardRenderer::new().render_text(&board);
+    assert_eq!(ascii, "  +-----------------+\n8 | . . . . k . . . |\n7 | . . . . . . . . |\n6 | . . . . . . . . |\n5 | . . . . . . . . |\n\
+        4 | . . . . P . . . |\n3 | . . . . . . . . |\n2 | . . . . . . . . |\n1 | . . . . K . . R |\n  +-----------------+\n    a b c d e f g h\n");
+    let flipped = BoardRenderer::new().set_perspective(Color::Black).set_coordinates(false).render_text(&board);
+    assert_eq!(flipped.lines().nth(1), Some("  | R . . K . . . . |"));
+    assert_eq!(flipped.lines().count(), 10);
+    let unicode = BoardRenderer::new().set_style(RenderStyle::Unicode).render_text(&board);
+    assert!(unicode.contains("4 | . . . . ♟ . . . |") && unicode.contains("8 | . . . . ♔ . . . |"));
+    let ansi = BoardRenderer::new().set_style(RenderStyle::Ansi).set_last_move(Square(12), Square(28)).render_text(&board);
+    assert_eq!(ansi.matches("\x1b[48;5;143m").count(), 2);
+    assert_eq!(ansi.lines().last(), Some("   a  b  c  d  e  f  g  h"));
+
+    let svg = BoardRenderer::new().set_last_move(Square(12), Square(28)).add_arrow(Square(7), Square(5)).render_svg(&board);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\"") && svg.ends_with("</svg>\n"));
+    assert_eq!(svg.matches("<rect").count(), 1 + 64 + 2);
+    assert_eq!(svg.matches("<line").count(), 1);
+    // 16 coordinates and 4 pieces
+    assert_eq!(svg.matches("<text").count(), 16 + 4);
+    assert!(BoardRenderer::new().set_coordinates(false).render_svg(&board).matches("<text").count() == 4);
+
+    let display = board.to_string();
+    assert!(display.contains("1 | . . . . ♚ . . ♜ |") && display.contains("    a b c d e f g h"));
+}
+
+#[test]
+fn test_display_position() {
+    // the rook on e8 and the knight on d3 both check the white king
+    let board = FenString::new("4r1k1/8/8/8/8/3n4/8/4K3 w - - 0 1".to_string()).convert_to_board();
+    assert!(board.get_checkers() == BitBoard::from(Square(19)) | BitBoard::from(Square(60)));
+    let display = display_position(&board, false);
+    assert!(display.contains("4r1k1/8/8/8/8/3n4/8/4K3 w - - 0 1"));
+    assert!(display.contains(&format!("Key: {:016X}", board.get_hash())));
+    assert!(display.contains("Side to move: white\nCheckers: d3 e8\nLegal moves (3): e1d1 e1f1 e1d2"));
+
+    let start = FenString::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string()).convert_to_board();
+    assert!(start.get_checkers().is_empty());
+    assert!(display_position(&start, false).contains("Checkers: -\nLegal moves (20): "));
+}
+
+#[test]
+fn test_xboard() {
+    let mut xboard = XBoardState::new(UciInformation::new());
+    let fen = |xboard: &XBoardState| FenString::from_board(xboard.game.get_board()).get_fen_string();
+    assert!(xboard.handle_command("protover 2") && xboard.handle_command("new") && xboard.handle_command("sd 1"));
+    // the engine plays black after new, ping waits for its reply
+    xboard.handle_command("usermove e2e4");
+    xboard.handle_command("ping 1");
+    assert!(!xboard.is_searching() && xboard.game.get_moves().count() == 2);
+
+    xboard.handle_command("force");
+    xboard.handle_command("usermove Nf3");
+    xboard.handle_command("usermove e2e5");
+    assert_eq!(xboard.game.get_moves().count(), 3);
+    xboard.handle_command("remove");
+    xboard.handle_command("undo");
+    assert_eq!(fen(&xboard), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+    xboard.handle_command("level 40 0:30 2");
+    assert_eq!(xboard.get_time_limit(), Some(30000 / 40 + 2000));
+    xboard.handle_command("time 1000");
+    assert_eq!(xboard.get_time_limit(), Some(10000 / 40 + 2000));
+    xboard.handle_command("st 2");
+    assert_eq!(xboard.get_time_limit(), Some(2000));
+
+    // a finished game (stalemate) is claimed instead of searched, an invalid setboard keeps the game
+    xboard.handle_command("setboard 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+    xboard.handle_command("go");
+    assert!(!xboard.is_searching() && xboard.engine_color == Some(Color::Black));
+    xboard.handle_command("setboard 8/8/8/8/8/8/8/8 w - - 0 1");
+    assert_eq!(fen(&xboard), "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+    // black to move could not answer a position where white can take the king
+    xboard.handle_command("setboard 7k/8/8/8/8/8/8/K6R w - - 0 1");
+    xboard.handle_command("go");
+    assert_eq!(fen(&xboard), "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+    assert!(!xboard.handle_command("quit"));
+}